@@ -1,9 +1,7 @@
-extern crate base64;
 extern crate imap;
 extern crate native_tls;
 
 use native_tls::TlsConnector;
-use base64::encode;
 use imap::client::Client;
 use imap::authenticator::Authenticator;
 
@@ -14,14 +12,12 @@ struct GmailOAuth2 {
 
 impl Authenticator for GmailOAuth2 {
     #[allow(unused_variables)]
-    fn process(&self, data: String) -> String {
-        encode(
-            format!(
-                "user={}\x01auth=Bearer {}\x01\x01",
-                self.user,
-                self.access_token
-            ).as_bytes(),
-        )
+    fn process(&mut self, data: &[u8]) -> Vec<u8> {
+        format!(
+            "user={}\x01auth=Bearer {}\x01\x01",
+            self.user,
+            self.access_token
+        ).into_bytes()
     }
 }
 
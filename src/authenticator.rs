@@ -0,0 +1,12 @@
+/// To implement an authenticator, you need to implement this trait and define a `process` method
+/// that does the right thing.
+///
+/// The trait method takes a challenge passed from the server, and returns a response to be sent
+/// back. Both the challenge and the response are base64-decoded/encoded by the caller, so the
+/// implementation only deals with the raw bytes. `process` is called once per challenge, so a
+/// multi-round mechanism such as SCRAM-SHA-256 or GSSAPI can keep whatever state it needs (e.g. a
+/// client nonce or salted password) on `self` between rounds.
+pub trait Authenticator {
+    /// Process the base64-decoded challenge data, returning the response to send back.
+    fn process(&mut self, data: &[u8]) -> Vec<u8>;
+}
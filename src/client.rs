@@ -1,7 +1,5 @@
-extern crate base64;
 use bufstream::BufStream;
-use native_tls::{TlsConnector, TlsStream};
-use nom;
+use imap_proto;
 use std::collections::HashSet;
 use std::io::{self, Read, Write};
 use std::net::{TcpStream, ToSocketAddrs};
@@ -12,9 +10,12 @@ use std::time::Duration;
 use super::authenticator::Authenticator;
 use super::error::{Error, ParseError, Result, ValidateError};
 use super::parse::{
-    parse_authenticate_response, parse_capabilities, parse_fetches, parse_ids, parse_mailbox,
-    parse_names,
+    fetch_from_attrs, parse_append_uid, parse_authenticate_response, parse_capabilities,
+    parse_copy_uid, parse_enabled, parse_fetches, parse_ids, parse_mailbox, parse_metadata,
+    parse_names, parse_search_return, to_unsolicited,
 };
+use super::mutf7;
+use super::tls::TlsConnector;
 use super::types::*;
 
 static TAG_PREFIX: &'static str = "a";
@@ -22,12 +23,26 @@ const INITIAL_TAG: u32 = 0;
 const CR: u8 = 0x0d;
 const LF: u8 = 0x0a;
 
+// The default per-command read timeout, chosen to match the 29-minute inactivity timeout RFC
+// 2177 warns servers may apply, with a minute of slack.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(28 * 60);
+
 macro_rules! quote {
     ($x:expr) => {
         format!("\"{}\"", $x.replace(r"\", r"\\").replace("\"", "\\\""))
     };
 }
 
+// Turns a raw `io::Error` from a read into the error a caller should see: a `TimedOut`/
+// `WouldBlock` from a configured read timeout becomes the typed `Error::Timeout` rather than an
+// opaque `Error::Io`, so callers can distinguish "the command timed out" from other I/O failures.
+fn classify_io_err(e: io::Error) -> Error {
+    match e.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => Error::Timeout,
+        _ => Error::Io(e),
+    }
+}
+
 fn validate_str(value: &str) -> Result<String> {
     let quoted = quote!(value);
     if quoted.find('\n').is_some() {
@@ -39,13 +54,44 @@ fn validate_str(value: &str) -> Result<String> {
     Ok(quoted)
 }
 
+// Like `validate_str`, but additionally encodes `mailbox` into the modified UTF-7 ([RFC 3501
+// section 5.1.3](https://tools.ietf.org/html/rfc3501#section-5.1.3)) form the wire protocol
+// expects, so callers can pass mailbox names containing non-ASCII characters as plain Unicode.
+fn validate_mailbox(mailbox: &str) -> Result<String> {
+    validate_str(&mutf7::encode(mailbox))
+}
+
+// If `line` is a syntactically complete response except for a trailing literal specifier
+// (`{1234}`/non-synchronizing `{1234+}`) whose payload hasn't arrived yet, returns the byte
+// count of that literal. This defers to `imap_proto`'s own grammar via `nom::Err::Incomplete`
+// rather than guessing from a trailing `{n}` byte pattern, so free-form resp-text that happens
+// to end in something that looks like a literal specifier (e.g. `OK [ALERT] quota exceeded
+// {1000}`) isn't misread as introducing one: that line parses as a complete response, not an
+// incomplete one, so `parse_response` returns `Ok` rather than `Incomplete`.
+fn pending_literal_len(line: &[u8]) -> Option<usize> {
+    match imap_proto::parse_response(line) {
+        Err(nom::Err::Incomplete(nom::Needed::Size(n))) => Some(usize::from(n)),
+        _ => None,
+    }
+}
+
+// Renders an already-expanded uid-set (e.g. as carried by a `COPYUID` response code) back into
+// the comma-separated wire form a `uid-set` grammar production accepts, so it can be embedded in
+// a synthetic response line and parsed back out again.
+fn uid_set(uids: &[u32]) -> String {
+    uids.iter()
+        .map(|u| u.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 /// An authenticated IMAP session providing the usual IMAP commands. This type is what you get from
 /// a succesful login attempt.
 ///
 /// Both `Client` and `Session` deref to [`Connection`](struct.Connection.html), the underlying
 /// primitives type.
 #[derive(Debug)]
-pub struct Session<T: Read + Write> {
+pub struct Session<T: Read + Write + SetReadTimeout> {
     conn: Connection<T>,
     /// Server responses that are not related to the current command. See also the note on
     /// [unilateral server responses in RFC 3501](https://tools.ietf.org/html/rfc3501#section-7).
@@ -60,22 +106,29 @@ pub struct Session<T: Read + Write> {
 /// Both `Client` and `Session` deref to [`Connection`](struct.Connection.html), the underlying
 /// primitives type.
 #[derive(Debug)]
-pub struct Client<T: Read + Write> {
+pub struct Client<T: Read + Write + SetReadTimeout> {
     conn: Connection<T>,
 }
 
 /// The underlying primitives type. Both `Client`(unauthenticated) and `Session`(after succesful
 /// login) use a `Connection` internally for the TCP stream primitives.
 #[derive(Debug)]
-pub struct Connection<T: Read + Write> {
+pub struct Connection<T: Read + Write + SetReadTimeout> {
     stream: BufStream<T>,
     tag: u32,
     pub debug: bool,
+    timeout: Option<Duration>,
+    enabled: Capabilities,
+    // Cached from the last call to `capabilities()`, so `write_command_with_literal` can tell
+    // whether the server supports non-synchronizing literals without forcing every caller to
+    // thread a `Capabilities` through. `None` until `capabilities()` has been called at least
+    // once.
+    capabilities: Option<Capabilities>,
 }
 
 // `Deref` instances are so we can make use of the same underlying primitives in `Client` and
 // `Session`
-impl<T: Read + Write> Deref for Client<T> {
+impl<T: Read + Write + SetReadTimeout> Deref for Client<T> {
     type Target = Connection<T>;
 
     fn deref(&self) -> &Connection<T> {
@@ -83,13 +136,13 @@ impl<T: Read + Write> Deref for Client<T> {
     }
 }
 
-impl<T: Read + Write> DerefMut for Client<T> {
+impl<T: Read + Write + SetReadTimeout> DerefMut for Client<T> {
     fn deref_mut(&mut self) -> &mut Connection<T> {
         &mut self.conn
     }
 }
 
-impl<T: Read + Write> Deref for Session<T> {
+impl<T: Read + Write + SetReadTimeout> Deref for Session<T> {
     type Target = Connection<T>;
 
     fn deref(&self) -> &Connection<T> {
@@ -97,7 +150,7 @@ impl<T: Read + Write> Deref for Session<T> {
     }
 }
 
-impl<T: Read + Write> DerefMut for Session<T> {
+impl<T: Read + Write + SetReadTimeout> DerefMut for Session<T> {
     fn deref_mut(&mut self) -> &mut Connection<T> {
         &mut self.conn
     }
@@ -110,7 +163,7 @@ impl<T: Read + Write> DerefMut for Session<T> {
 ///
 /// As long a the handle is active, the mailbox cannot be otherwise accessed.
 #[derive(Debug)]
-pub struct IdleHandle<'a, T: Read + Write + 'a> {
+pub struct IdleHandle<'a, T: Read + Write + SetReadTimeout + 'a> {
     session: &'a mut Session<T>,
     keepalive: Duration,
     done: bool,
@@ -130,7 +183,7 @@ pub trait SetReadTimeout {
     fn set_read_timeout(&mut self, timeout: Option<Duration>) -> Result<()>;
 }
 
-impl<'a, T: Read + Write + 'a> IdleHandle<'a, T> {
+impl<'a, T: Read + Write + SetReadTimeout + 'a> IdleHandle<'a, T> {
     fn new(session: &'a mut Session<T>) -> Result<Self> {
         let mut h = IdleHandle {
             session,
@@ -176,28 +229,72 @@ impl<'a, T: Read + Write + 'a> IdleHandle<'a, T> {
     /// Internal helper that doesn't consume self.
     ///
     /// This is necessary so that we can keep using the inner `Session` in `wait_keepalive`.
-    fn wait_inner(&mut self) -> Result<()> {
-        let mut v = Vec::new();
-        match self.session.readline(&mut v).map(|_| ()) {
-            Err(Error::Io(ref e))
-                if e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::WouldBlock =>
-            {
-                // we need to refresh the IDLE connection
-                self.terminate()?;
-                self.init()?;
-                self.wait_inner()
+    ///
+    /// Reads untagged responses off the IDLE connection one at a time. Each one that parses as an
+    /// `UnsolicitedResponse` (`EXISTS`, `RECENT`, `EXPUNGE`, or `FETCH`) is sent to
+    /// `Session::unsolicited_responses` and handed to `callback`; idling continues for as long as
+    /// `callback` returns `true`. Anything that doesn't parse as one of those, or a `false` return
+    /// from `callback`, ends the wait.
+    fn wait_inner_while<F>(&mut self, callback: &mut F) -> Result<()>
+    where
+        F: FnMut(UnsolicitedResponse) -> bool,
+    {
+        loop {
+            let mut v = Vec::new();
+            match self.session.readline(&mut v) {
+                Err(Error::Timeout) => {
+                    // we need to refresh the IDLE connection
+                    self.terminate()?;
+                    self.init()?;
+                    continue;
+                }
+                Err(e) => return Err(e),
+                Ok(_) => {}
+            }
+
+            let event = match imap_proto::parse_response(&v) {
+                Ok((_, resp)) => to_unsolicited(resp).ok(),
+                Err(_) => None,
+            };
+            match event {
+                Some(r) => {
+                    self.session
+                        .unsolicited_responses_tx
+                        .send(r.clone())
+                        .unwrap();
+                    if !callback(r) {
+                        return Ok(());
+                    }
+                }
+                None => return Ok(()),
             }
-            r => r,
         }
     }
 
+    fn wait_inner(&mut self) -> Result<()> {
+        self.wait_inner_while(&mut |_| false)
+    }
+
     /// Block until the selected mailbox changes.
     pub fn wait(mut self) -> Result<()> {
         self.wait_inner()
     }
-}
 
-impl<'a, T: SetReadTimeout + Read + Write + 'a> IdleHandle<'a, T> {
+    /// Block until `callback` says to stop, inspecting each notification as it arrives.
+    ///
+    /// `callback` is invoked with every `UnsolicitedResponse` the server sends while idling, which
+    /// are also, as usual, sent to
+    /// [`Session::unsolicited_responses`](struct.Session.html#structfield.unsolicited_responses).
+    /// Idling continues for as long as `callback` returns `true`; returning `false` ends the wait,
+    /// letting long-lived clients react to individual events without tearing down and
+    /// re-establishing the IDLE session on every notification.
+    pub fn wait_while<F>(mut self, mut callback: F) -> Result<()>
+    where
+        F: FnMut(UnsolicitedResponse) -> bool,
+    {
+        self.wait_inner_while(&mut callback)
+    }
+
     /// Set the keep-alive interval to use when `wait_keepalive` is called.
     ///
     /// The interval defaults to 29 minutes as dictated by RFC 2177.
@@ -227,35 +324,110 @@ impl<'a, T: SetReadTimeout + Read + Write + 'a> IdleHandle<'a, T> {
 
     /// Block until the selected mailbox changes, or until the given amount of time has expired.
     pub fn wait_timeout(mut self, timeout: Duration) -> Result<()> {
+        let default_timeout = self.session.timeout;
         self.session
             .stream
             .get_mut()
             .set_read_timeout(Some(timeout))?;
         let res = self.wait_inner();
-        self.session.stream.get_mut().set_read_timeout(None).is_ok();
+        self.session
+            .stream
+            .get_mut()
+            .set_read_timeout(default_timeout)
+            .is_ok();
         res
     }
 }
 
-impl<'a, T: Read + Write + 'a> Drop for IdleHandle<'a, T> {
+impl<'a, T: Read + Write + SetReadTimeout + 'a> Drop for IdleHandle<'a, T> {
     fn drop(&mut self) {
         // we don't want to panic here if we can't terminate the Idle
         self.terminate().is_ok();
     }
 }
 
-impl<'a> SetReadTimeout for TcpStream {
-    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
-        TcpStream::set_read_timeout(self, timeout).map_err(Error::Io)
+/// A builder for the `APPEND` command, returned by [`Session::append`](struct.Session.html#method.append).
+///
+/// Call [`flags`](#method.flags) and/or [`internal_date`](#method.internal_date) to attach
+/// optional APPEND arguments per [RFC 3501 section
+/// 6.3.11](https://tools.ietf.org/html/rfc3501#section-6.3.11), then [`finish`](#method.finish)
+/// to send the command.
+#[derive(Debug)]
+pub struct Append<'a, T: Read + Write + SetReadTimeout + 'a> {
+    session: &'a mut Session<T>,
+    folder: &'a str,
+    content: &'a [u8],
+    flags: Option<String>,
+    internal_date: Option<String>,
+}
+
+impl<'a, T: Read + Write + SetReadTimeout + 'a> Append<'a, T> {
+    fn new(session: &'a mut Session<T>, folder: &'a str, content: &'a [u8]) -> Self {
+        Append {
+            session,
+            folder,
+            content,
+            flags: None,
+            internal_date: None,
+        }
+    }
+
+    /// Sets the flags (e.g. `\Seen`) the appended message should be stored with.
+    pub fn flags(mut self, flags: &[&str]) -> Self {
+        self.flags = Some(format!("({})", flags.join(" ")));
+        self
+    }
+
+    /// Sets the internal date the appended message should be stored with, formatted per the
+    /// `date-time` production of [RFC 3501 section
+    /// 9](https://tools.ietf.org/html/rfc3501#section-9), e.g. `"01-Jan-2024 00:00:00 +0000"`.
+    pub fn internal_date(mut self, date: &str) -> Self {
+        self.internal_date = Some(quote!(date));
+        self
+    }
+
+    /// Sends the `APPEND` command, returning the UID the server assigned the new message via the
+    /// `[APPENDUID <uidvalidity> <uid>]` response code ([RFC
+    /// 4315](https://tools.ietf.org/html/rfc4315#section-3)), or `None` if the server doesn't
+    /// support UIDPLUS.
+    pub fn finish(self) -> Result<Option<AppendUid>> {
+        let session = self.session;
+        let utf8 = session.enabled.has("UTF8=ACCEPT");
+
+        let mut prefix = format!("APPEND {}", quote!(mutf7::encode(self.folder)));
+        if let Some(flags) = &self.flags {
+            prefix.push(' ');
+            prefix.push_str(flags);
+        }
+        if let Some(internal_date) = &self.internal_date {
+            prefix.push(' ');
+            prefix.push_str(internal_date);
+        }
+        prefix.push(' ');
+        if utf8 {
+            prefix.push_str("UTF8 (");
+        }
+
+        session.write_command_with_literal(&prefix, self.content, utf8)?;
+        if utf8 {
+            session.stream.write_all(b")")?;
+        }
+        session.stream.write_all(b"\r\n")?;
+        session.stream.flush()?;
+        let data = session.read_response()?;
+        parse_append_uid(&data)
     }
 }
 
-impl<'a> SetReadTimeout for TlsStream<TcpStream> {
+impl<'a> SetReadTimeout for TcpStream {
     fn set_read_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
-        self.get_ref().set_read_timeout(timeout).map_err(Error::Io)
+        TcpStream::set_read_timeout(self, timeout).map_err(Error::Io)
     }
 }
 
+// `SetReadTimeout` impls for the streams produced by each TLS backend live alongside their
+// `TlsConnector` impls in `tls.rs`, behind the same feature flags.
+
 /// Creates a new client. The usual IMAP commands are part of the [`Session`](struct.Session.html)
 /// type, returned from a succesful call to [`Client::login`](struct.Client.html#method.login).
 /// ```rust,no_run
@@ -287,6 +459,10 @@ pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Client<TcpStream>> {
 /// Creates a `Client` with an SSL wrapper. The usual IMAP commands are part of the
 /// [`Session`](struct.Session.html) type, returned from a succesful call to
 /// [`Client::login`](struct.Client.html#method.login).
+///
+/// `ssl_connector` may be any backend implementing [`TlsConnector`](../tls/trait.TlsConnector.html),
+/// such as a [`native_tls::TlsConnector`] (with the `native-tls` feature) or a
+/// [`tls::RustlsConnector`](../tls/struct.RustlsConnector.html) (with the `rustls-tls` feature).
 /// ```rust,no_run
 /// # extern crate native_tls;
 /// # extern crate imap;
@@ -300,17 +476,17 @@ pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Client<TcpStream>> {
 ///     &ssl_connector).unwrap();
 /// # }
 /// ```
-pub fn secure_connect<A: ToSocketAddrs>(
+pub fn secure_connect<A: ToSocketAddrs, C: TlsConnector<TcpStream>>(
     addr: A,
     domain: &str,
-    ssl_connector: &TlsConnector,
-) -> Result<Client<TlsStream<TcpStream>>> {
+    ssl_connector: &C,
+) -> Result<Client<C::Stream>>
+where
+    C::Stream: SetReadTimeout,
+{
     match TcpStream::connect(addr) {
         Ok(stream) => {
-            let ssl_stream = match TlsConnector::connect(ssl_connector, domain, stream) {
-                Ok(s) => s,
-                Err(e) => return Err(Error::TlsHandshake(e)),
-            };
+            let ssl_stream = ssl_connector.connect(domain, stream)?;
             let mut socket = Client::new(ssl_stream);
 
             socket.read_greeting()?;
@@ -320,20 +496,77 @@ pub fn secure_connect<A: ToSocketAddrs>(
     }
 }
 
+/// Opens a cleartext connection to `addr` and immediately upgrades it to TLS via `STARTTLS`
+/// ([RFC 3501 section 6.2.1](https://tools.ietf.org/html/rfc3501#section-6.2.1)), returning a
+/// `Client` wrapping the resulting encrypted stream. Use this for servers that only offer
+/// encryption via `STARTTLS` on their plaintext port (usually 143); for servers that expect TLS
+/// from the very first byte (usually port 993), use [`secure_connect`] instead.
+///
+/// No credentials are sent, and no `CAPABILITY` response from before the handshake is trusted or
+/// reused; see the note on [`Client::secure`](struct.Client.html#method.secure) for how that's
+/// enforced.
+/// ```rust,no_run
+/// # extern crate native_tls;
+/// # extern crate imap;
+/// # use std::io;
+/// # use native_tls::TlsConnector;
+/// # fn main() {
+/// let ssl_connector = TlsConnector::builder().build().unwrap();
+/// let client = imap::client::connect_starttls(
+///     ("imap.example.org", 143),
+///     "imap.example.org",
+///     &ssl_connector).unwrap();
+/// # }
+/// ```
+pub fn connect_starttls<A: ToSocketAddrs, C: TlsConnector<TcpStream>>(
+    addr: A,
+    domain: &str,
+    ssl_connector: &C,
+) -> Result<Client<C::Stream>>
+where
+    C::Stream: SetReadTimeout,
+{
+    match TcpStream::connect(addr) {
+        Ok(stream) => {
+            let mut client = Client::new(stream);
+            client.read_greeting()?;
+            client.secure(domain, ssl_connector)
+        }
+        Err(e) => Err(Error::Io(e)),
+    }
+}
+
 impl Client<TcpStream> {
     /// This will upgrade a regular TCP connection to use SSL.
     ///
     /// Use the domain parameter for openssl's SNI and hostname verification.
-    pub fn secure(
+    pub fn secure<C: TlsConnector<TcpStream>>(
         mut self,
         domain: &str,
-        ssl_connector: &TlsConnector,
-    ) -> Result<Client<TlsStream<TcpStream>>> {
+        ssl_connector: &C,
+    ) -> Result<Client<C::Stream>>
+    where
+        C::Stream: SetReadTimeout,
+    {
         // TODO This needs to be tested
         self.run_command_and_check_ok("STARTTLS")?;
-        TlsConnector::connect(ssl_connector, domain, self.conn.stream.into_inner()?)
-            .map(Client::new)
-            .map_err(Error::TlsHandshake)
+        // `into_inner` drops any data still sitting in the `BufStream`'s read buffer rather than
+        // handing it to the TLS handshake, so a network attacker who injected plaintext after the
+        // `STARTTLS` response (but before the handshake) can't have it interpreted as though it
+        // arrived over the encrypted connection.
+        let mut client = ssl_connector
+            .connect(domain, self.conn.stream.into_inner()?)
+            .map(Client::new)?;
+        // The capability list the server advertised before the handshake (if any was fetched) is
+        // untrusted, since it too was sent in the clear; `Client::new` already starts the
+        // upgraded client with an empty capability cache, but we also proactively re-fetch it
+        // here so `client.capabilities` reflects what the server actually supports post-upgrade.
+        let (mut tx, _rx) = mpsc::channel();
+        let capabilities = client
+            .run_command_and_read_response("CAPABILITY")
+            .and_then(|lines| parse_capabilities(&lines[..], &mut tx))?;
+        client.capabilities = Some(capabilities);
+        Ok(client)
     }
 }
 
@@ -353,14 +586,24 @@ macro_rules! ok_or_unauth_client_err {
     };
 }
 
-impl<T: Read + Write> Client<T> {
+impl<T: Read + Write + SetReadTimeout> Client<T> {
     /// Creates a new client with the underlying stream.
-    pub fn new(stream: T) -> Client<T> {
+    ///
+    /// The connection is given a default read timeout (see `Connection::set_timeout`) so that a
+    /// server that stops responding mid-command doesn't hang the caller forever; use
+    /// `set_timeout` to change or disable it.
+    pub fn new(mut stream: T) -> Client<T> {
+        // Best-effort: if the stream can't have a read timeout set (e.g. a test mock), commands
+        // simply won't time out rather than `new` failing.
+        stream.set_read_timeout(Some(DEFAULT_TIMEOUT)).is_ok();
         Client {
             conn: Connection {
                 stream: BufStream::new(stream),
                 tag: INITIAL_TAG,
                 debug: false,
+                timeout: Some(DEFAULT_TIMEOUT),
+                enabled: Capabilities(Vec::new()),
+                capabilities: None,
             },
         }
     }
@@ -369,19 +612,22 @@ impl<T: Read + Write> Client<T> {
     pub fn authenticate<A: Authenticator>(
         mut self,
         auth_type: &str,
-        authenticator: A,
+        mut authenticator: A,
     ) -> ::std::result::Result<Session<T>, (Error, Client<T>)> {
         ok_or_unauth_client_err!(
             self.run_command(&format!("AUTHENTICATE {}", auth_type)),
             self
         );
-        self.do_auth_handshake(&authenticator)
+        self.do_auth_handshake(&mut authenticator)
     }
 
-    /// This func does the handshake process once the authenticate command is made.
+    /// This func does the handshake process once the authenticate command is made. Mechanisms
+    /// such as SCRAM-SHA-256 and GSSAPI take several challenge/response round-trips, so this
+    /// loops, feeding each `+ <base64>` continuation to the authenticator and sending back its
+    /// (base64-encoded) reply, until the tagged response arrives.
     fn do_auth_handshake<A: Authenticator>(
         mut self,
-        authenticator: &A,
+        authenticator: &mut A,
     ) -> ::std::result::Result<Session<T>, (Error, Client<T>)> {
         // TODO Clean up this code
         loop {
@@ -395,10 +641,10 @@ impl<T: Read + Write> Client<T> {
                     parse_authenticate_response(String::from_utf8(line).unwrap()),
                     self
                 );
-                let auth_response = authenticator.process(data);
+                let auth_response = authenticator.process(&data);
 
                 ok_or_unauth_client_err!(
-                    self.write_line(auth_response.into_bytes().as_slice()),
+                    self.write_line(base64::encode(&auth_response).as_bytes()),
                     self
                 );
             } else {
@@ -454,7 +700,7 @@ impl<T: Read + Write> Client<T> {
     }
 }
 
-impl<T: Read + Write> Session<T> {
+impl<T: Read + Write + SetReadTimeout> Session<T> {
     // not public, just to avoid duplicating the channel creation code
     fn new(conn: Connection<T>) -> Self {
         let (tx, rx) = mpsc::channel();
@@ -470,24 +716,65 @@ impl<T: Read + Write> Session<T> {
     /// `EXISTS`, `FETCH`, and `EXPUNGE` responses. You can get them from the
     /// `unsolicited_responses` channel of the [`Session`](struct.Session.html).
     pub fn select(&mut self, mailbox_name: &str) -> Result<Mailbox> {
-        self.run_command_and_read_response(&format!("SELECT {}", validate_str(mailbox_name)?))
+        self.run_command_and_read_response(&format!("SELECT {}", validate_mailbox(mailbox_name)?))
             .and_then(|lines| parse_mailbox(&lines[..], &mut self.unsolicited_responses_tx))
     }
 
     /// Examine is identical to Select, but the selected mailbox is identified as read-only
     pub fn examine(&mut self, mailbox_name: &str) -> Result<Mailbox> {
-        self.run_command_and_read_response(&format!("EXAMINE {}", validate_str(mailbox_name)?))
+        self.run_command_and_read_response(&format!("EXAMINE {}", validate_mailbox(mailbox_name)?))
             .and_then(|lines| parse_mailbox(&lines[..], &mut self.unsolicited_responses_tx))
     }
 
+    /// Like [`select`](#method.select), but asks the server to enable `CONDSTORE` (RFC 7162) for
+    /// this mailbox, so its `HIGHESTMODSEQ` is reported on [`Mailbox::highest_mod_seq`] and
+    /// subsequent `FETCH`/`STORE` commands can use mod-sequence modifiers.
+    pub fn select_condstore(&mut self, mailbox_name: &str) -> Result<Mailbox> {
+        self.run_command_and_read_response(&format!(
+            "SELECT {} (CONDSTORE)",
+            validate_mailbox(mailbox_name)?
+        ))
+        .and_then(|lines| parse_mailbox(&lines[..], &mut self.unsolicited_responses_tx))
+    }
+
+    /// Like [`select_condstore`](#method.select_condstore), but additionally asks the server to
+    /// `QRESYNC` (RFC 7162) this mailbox against the last-known `uid_validity` and `mod_seq`, so
+    /// the client can resync its cache instead of re-fetching everything. `known_uids` is an
+    /// optional UID set of the messages the client already knows about, which lets the server
+    /// limit the `VANISHED` response to messages within that set.
+    ///
+    /// Any `VANISHED (EARLIER)` UIDs and changed `FETCH` responses the server includes in reply
+    /// are sent to the `unsolicited_responses` channel of the [`Session`](struct.Session.html) as
+    /// [`UnsolicitedResponse::Vanished`] and [`UnsolicitedResponse::Fetch`].
+    pub fn select_qresync(
+        &mut self,
+        mailbox_name: &str,
+        uid_validity: u32,
+        mod_seq: u64,
+        known_uids: Option<&str>,
+    ) -> Result<Mailbox> {
+        let known_uids = known_uids.map(|u| format!(" {}", u)).unwrap_or_default();
+        self.run_command_and_read_response(&format!(
+            "SELECT {} (QRESYNC ({} {}{}))",
+            validate_mailbox(mailbox_name)?,
+            uid_validity,
+            mod_seq,
+            known_uids
+        ))
+        .and_then(|lines| parse_mailbox(&lines[..], &mut self.unsolicited_responses_tx))
+    }
+
     /// Fetch retreives data associated with a set of messages in the mailbox.
     ///
     /// Note that the server *is* allowed to unilaterally include `FETCH` responses for other
     /// messages in the selected mailbox whose status has changed. See the note on [unilateral
     /// server responses in RFC 3501](https://tools.ietf.org/html/rfc3501#section-7).
     pub fn fetch(&mut self, sequence_set: &str, query: &str) -> ZeroCopyResult<Vec<Fetch>> {
-        self.run_command_and_read_response(&format!("FETCH {} {}", sequence_set, query))
-            .and_then(|lines| parse_fetches(lines, &mut self.unsolicited_responses_tx))
+        self.run_command(&format!("FETCH {} {}", sequence_set, query))?;
+        let mut fetches = Vec::new();
+        let mut vanished = Vec::new();
+        self.read_fetches_onto(&mut fetches, &mut vanished)?;
+        Ok(fetches)
     }
 
     /// Fetch retreives data associated with a set of messages by UID in the mailbox.
@@ -496,8 +783,114 @@ impl<T: Read + Write> Session<T> {
     /// messages in the selected mailbox whose status has changed. See the note on [unilateral
     /// server responses in RFC 3501](https://tools.ietf.org/html/rfc3501#section-7).
     pub fn uid_fetch(&mut self, uid_set: &str, query: &str) -> ZeroCopyResult<Vec<Fetch>> {
-        self.run_command_and_read_response(&format!("UID FETCH {} {}", uid_set, query))
-            .and_then(|lines| parse_fetches(lines, &mut self.unsolicited_responses_tx))
+        self.run_command(&format!("UID FETCH {} {}", uid_set, query))?;
+        let mut fetches = Vec::new();
+        let mut vanished = Vec::new();
+        self.read_fetches_onto(&mut fetches, &mut vanished)?;
+        Ok(fetches)
+    }
+
+    /// Like [`fetch`](#method.fetch), but for a `CONDSTORE`-enabled mailbox ([RFC
+    /// 7162](https://tools.ietf.org/html/rfc7162)): only messages whose `MODSEQ` has changed
+    /// since `mod_seq` are returned. On a `QRESYNC`-enabled connection the server may also report
+    /// messages expunged since `mod_seq` as `VANISHED (EARLIER) <uid-set>`; those UIDs are
+    /// returned alongside the fetches rather than being folded into them.
+    pub fn fetch_changedsince(
+        &mut self,
+        sequence_set: &str,
+        mod_seq: u64,
+        query: &str,
+    ) -> ZeroCopyResult<(Vec<Fetch>, Vec<u32>)> {
+        self.run_command(&format!(
+            "FETCH {} {} (CHANGEDSINCE {})",
+            sequence_set, query, mod_seq
+        ))?;
+        let mut fetches = Vec::new();
+        let mut vanished = Vec::new();
+        self.read_fetches_onto(&mut fetches, &mut vanished)?;
+        Ok((fetches, vanished))
+    }
+
+    /// Like [`fetch_changedsince`](#method.fetch_changedsince), but addressing messages by UID.
+    pub fn uid_fetch_changedsince(
+        &mut self,
+        uid_set: &str,
+        mod_seq: u64,
+        query: &str,
+    ) -> ZeroCopyResult<(Vec<Fetch>, Vec<u32>)> {
+        self.run_command(&format!(
+            "UID FETCH {} {} (CHANGEDSINCE {})",
+            uid_set, query, mod_seq
+        ))?;
+        let mut fetches = Vec::new();
+        let mut vanished = Vec::new();
+        self.read_fetches_onto(&mut fetches, &mut vanished)?;
+        Ok((fetches, vanished))
+    }
+
+    // Reads a FETCH-family command's response directly off the wire, converting each
+    // `* n FETCH (...)` to a `Fetch` the moment it's fully read (literal included) and pushing it
+    // onto `fetches` immediately, instead of buffering the whole multi-message reply into one
+    // `Vec<u8>` and re-parsing it afterward the way `parse_fetches` does. This bounds memory to
+    // one message's attributes at a time, which matters once a `BODY[]` literal can be an entire
+    // message. `VANISHED (EARLIER) <uid-set>` responses, which a QRESYNC `CHANGEDSINCE` fetch
+    // interleaves with the `FETCH` replies, are collected into `vanished`; any other untagged
+    // response is forwarded to `unsolicited_responses` exactly as `to_unsolicited` would.
+    fn read_fetches_onto(
+        &mut self,
+        fetches: &mut Vec<Fetch>,
+        vanished: &mut Vec<u32>,
+    ) -> Result<()> {
+        use imap_proto::{parse_response, Response, Status};
+
+        let mut buf = Vec::new();
+        let match_tag = format!("{}{}", TAG_PREFIX, self.tag);
+        loop {
+            self.readline(&mut buf)?;
+
+            let resp = match parse_response(&buf) {
+                Ok((_, resp)) => resp,
+                Err(nom::Err::Incomplete(..)) => continue,
+                _ => return Err(Error::Parse(ParseError::Invalid(buf))),
+            };
+
+            match resp {
+                Response::Done {
+                    tag,
+                    status,
+                    code,
+                    information,
+                    ..
+                } => {
+                    assert_eq!(tag.as_bytes(), match_tag.as_bytes());
+                    return match status {
+                        Status::Ok => Ok(()),
+                        Status::Bad => Err(Error::BadResponse(
+                            code.map(super::error::ResponseCode::from_imap_proto),
+                            information
+                                .map(|s| s.to_string())
+                                .unwrap_or_else(|| "no explanation given".to_string()),
+                        )),
+                        Status::No => Err(Error::NoResponse(
+                            code.map(super::error::ResponseCode::from_imap_proto),
+                            information
+                                .map(|s| s.to_string())
+                                .unwrap_or_else(|| "no explanation given".to_string()),
+                        )),
+                        _ => Err(Error::Parse(ParseError::Invalid(Vec::new()))),
+                    };
+                }
+                Response::Fetch(num, attrs) => fetches.push(fetch_from_attrs(num, attrs)),
+                Response::Vanished { uids, .. } => vanished.extend(uids.iter().cloned()),
+                resp => {
+                    if let Ok(r) = to_unsolicited(resp) {
+                        self.unsolicited_responses_tx.send(r).unwrap();
+                    }
+                }
+            }
+
+            buf.clear();
+        }
     }
 
     /// Noop always succeeds, and it does nothing.
@@ -512,39 +905,117 @@ impl<T: Read + Write> Session<T> {
 
     /// Create creates a mailbox with the given name.
     pub fn create(&mut self, mailbox_name: &str) -> Result<()> {
-        self.run_command_and_check_ok(&format!("CREATE {}", validate_str(mailbox_name)?))
+        self.run_command_and_check_ok(&format!("CREATE {}", validate_mailbox(mailbox_name)?))
     }
 
     /// Delete permanently removes the mailbox with the given name.
     pub fn delete(&mut self, mailbox_name: &str) -> Result<()> {
-        self.run_command_and_check_ok(&format!("DELETE {}", validate_str(mailbox_name)?))
+        self.run_command_and_check_ok(&format!("DELETE {}", validate_mailbox(mailbox_name)?))
     }
 
     /// Rename changes the name of a mailbox.
     pub fn rename(&mut self, current_mailbox_name: &str, new_mailbox_name: &str) -> Result<()> {
         self.run_command_and_check_ok(&format!(
             "RENAME {} {}",
-            quote!(current_mailbox_name),
-            quote!(new_mailbox_name)
+            quote!(mutf7::encode(current_mailbox_name)),
+            quote!(mutf7::encode(new_mailbox_name))
         ))
     }
 
     /// Subscribe adds the specified mailbox name to the server's set of "active" or "subscribed"
     /// mailboxes as returned by the LSUB command.
     pub fn subscribe(&mut self, mailbox: &str) -> Result<()> {
-        self.run_command_and_check_ok(&format!("SUBSCRIBE {}", quote!(mailbox)))
+        self.run_command_and_check_ok(&format!("SUBSCRIBE {}", quote!(mutf7::encode(mailbox))))
     }
 
     /// Unsubscribe removes the specified mailbox name from the server's set of
     /// "active" or "subscribed mailboxes as returned by the LSUB command.
     pub fn unsubscribe(&mut self, mailbox: &str) -> Result<()> {
-        self.run_command_and_check_ok(&format!("UNSUBSCRIBE {}", quote!(mailbox)))
+        self.run_command_and_check_ok(&format!("UNSUBSCRIBE {}", quote!(mutf7::encode(mailbox))))
     }
 
     /// Capability requests a listing of capabilities that the server supports.
     pub fn capabilities(&mut self) -> ZeroCopyResult<Capabilities> {
-        self.run_command_and_read_response("CAPABILITY")
-            .and_then(|lines| parse_capabilities(lines, &mut self.unsolicited_responses_tx))
+        let capabilities = self
+            .run_command_and_read_response("CAPABILITY")
+            .and_then(|lines| parse_capabilities(&lines[..], &mut self.unsolicited_responses_tx))?;
+        self.capabilities = Some(capabilities.clone());
+        Ok(capabilities)
+    }
+
+    /// Enable requests that the server enable the named extensions, per [RFC
+    /// 5161](https://tools.ietf.org/html/rfc5161). Extensions are only enabled for the
+    /// duration of the connection, and only take effect once the server confirms them; the
+    /// set of extensions the server actually agreed to enable is returned so callers can gate
+    /// subsequent commands on it instead of assuming success. The confirmed set is also recorded
+    /// and can be read back later via [`Connection::enabled`](struct.Connection.html#method.enabled).
+    ///
+    /// Enabling `UTF8=ACCEPT` ([RFC 6855](https://tools.ietf.org/html/rfc6855)) switches
+    /// [`append`](#method.append) to send its message body in the `UTF8 (...)` form rather than a
+    /// bare literal. Mailbox names are still sent in modified UTF-7 (see [`Name::name`])
+    /// regardless of `UTF8=ACCEPT`; enabling it does not switch them to UTF-8.
+    pub fn enable(&mut self, capabilities: &[&str]) -> ZeroCopyResult<Capabilities> {
+        let enabled = self
+            .run_command_and_read_response(&format!("ENABLE {}", capabilities.join(" ")))
+            .and_then(|lines| parse_enabled(&lines[..]))?;
+        // ENABLE is cumulative: once the server confirms an extension, it stays on for the rest
+        // of the connection, so fold the newly-confirmed set into what's already recorded.
+        self.enabled.0.extend(enabled.0.iter().cloned());
+        Ok(enabled)
+    }
+
+    /// Returns the server or mailbox annotations named by `entries` ([RFC
+    /// 5464](https://tools.ietf.org/html/rfc5464)). Pass `""` as `mailbox` to query server-wide
+    /// metadata. `options`, if given, is the raw `GETMETADATA` command-options list, e.g.
+    /// `Some("DEPTH 1")`, `Some("DEPTH infinity")`, or `Some("MAXSIZE 1024")`. Check the
+    /// `METADATA`/`METADATA-SERVER` capability with [`Session::capabilities`] before calling
+    /// this.
+    ///
+    /// Each returned pair is an entry name together with its value, or `None` if the server
+    /// reported the entry as `NIL`.
+    pub fn get_metadata(
+        &mut self,
+        mailbox: &str,
+        options: Option<&str>,
+        entries: &[&str],
+    ) -> ZeroCopyResult<Vec<(String, Option<Vec<u8>>)>> {
+        let options = options.map(|o| format!("({}) ", o)).unwrap_or_default();
+        let entries = entries
+            .iter()
+            .map(|e| quote!(e))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.run_command_and_read_response(&format!(
+            "GETMETADATA {}{} ({})",
+            options,
+            quote!(mutf7::encode(mailbox)),
+            entries
+        ))
+        .and_then(|lines| parse_metadata(&lines[..], &mut self.unsolicited_responses_tx))
+    }
+
+    /// Sets or deletes the given server or mailbox annotations ([RFC
+    /// 5464](https://tools.ietf.org/html/rfc5464)). Pass `""` as `mailbox` to set server-wide
+    /// metadata. A `None` value deletes the entry by sending `NIL`.
+    pub fn set_metadata(&mut self, mailbox: &str, entries: &[(&str, Option<&str>)]) -> Result<()> {
+        let entries = entries
+            .iter()
+            .map(|(entry, value)| {
+                format!(
+                    "{} {}",
+                    quote!(entry),
+                    value
+                        .map(|v| quote!(v))
+                        .unwrap_or_else(|| "NIL".to_string())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.run_command_and_check_ok(&format!(
+            "SETMETADATA {} ({})",
+            quote!(mutf7::encode(mailbox)),
+            entries
+        ))
     }
 
     /// Expunge permanently removes all messages that have the \Deleted flag set from the currently
@@ -571,47 +1042,118 @@ impl<T: Read + Write> Session<T> {
         self.run_command_and_check_ok("CLOSE")
     }
 
+    /// Like [`close`](#method.close), returns to the authenticated state from the selected state,
+    /// but without implicitly expunging `\Deleted` messages first. Requires the server to
+    /// advertise the `UNSELECT` capability ([RFC
+    /// 3691](https://tools.ietf.org/html/rfc3691)); if it doesn't, returns
+    /// [`Error::Unsupported`] rather than sending a command the server would just reject with
+    /// `BAD`.
+    pub fn unselect(&mut self) -> Result<()> {
+        if !self.capabilities()?.has("UNSELECT") {
+            return Err(Error::Unsupported("UNSELECT"));
+        }
+        self.run_command_and_check_ok("UNSELECT")
+    }
+
     /// Store alters data associated with a message in the mailbox.
     pub fn store(&mut self, sequence_set: &str, query: &str) -> ZeroCopyResult<Vec<Fetch>> {
         self.run_command_and_read_response(&format!("STORE {} {}", sequence_set, query))
-            .and_then(|lines| parse_fetches(lines, &mut self.unsolicited_responses_tx))
+            .and_then(|lines| parse_fetches(&lines[..], &mut self.unsolicited_responses_tx))
     }
 
     pub fn uid_store(&mut self, uid_set: &str, query: &str) -> ZeroCopyResult<Vec<Fetch>> {
         self.run_command_and_read_response(&format!("UID STORE {} {}", uid_set, query))
-            .and_then(|lines| parse_fetches(lines, &mut self.unsolicited_responses_tx))
+            .and_then(|lines| parse_fetches(&lines[..], &mut self.unsolicited_responses_tx))
+    }
+
+    /// Like [`store`](#method.store), but conditional on `CONDSTORE` (RFC 7162): the store is only
+    /// applied to messages whose `MODSEQ` is still `mod_seq` or lower. If any addressed messages
+    /// have since been modified, the command still succeeds for the rest, but returns
+    /// [`Error::Modified`] with the UIDs of the ones that were skipped.
+    pub fn store_unchangedsince(
+        &mut self,
+        sequence_set: &str,
+        mod_seq: u64,
+        query: &str,
+    ) -> ZeroCopyResult<Vec<Fetch>> {
+        self.run_command_and_read_response(&format!(
+            "STORE {} (UNCHANGEDSINCE {}) {}",
+            sequence_set, mod_seq, query
+        ))
+        .and_then(|lines| parse_fetches(&lines[..], &mut self.unsolicited_responses_tx))
+    }
+
+    /// Like [`store_unchangedsince`](#method.store_unchangedsince), but addressing messages by
+    /// UID.
+    pub fn uid_store_unchangedsince(
+        &mut self,
+        uid_set: &str,
+        mod_seq: u64,
+        query: &str,
+    ) -> ZeroCopyResult<Vec<Fetch>> {
+        self.run_command_and_read_response(&format!(
+            "UID STORE {} (UNCHANGEDSINCE {}) {}",
+            uid_set, mod_seq, query
+        ))
+        .and_then(|lines| parse_fetches(&lines[..], &mut self.unsolicited_responses_tx))
     }
 
     /// Copy copies the specified message to the end of the specified destination mailbox.
-    pub fn copy(&mut self, sequence_set: &str, mailbox_name: &str) -> Result<()> {
-        self.run_command_and_check_ok(&format!("COPY {} {}", sequence_set, mailbox_name))
+    ///
+    /// Returns the source→destination UID mapping reported via the `COPYUID` response code ([RFC
+    /// 4315](https://tools.ietf.org/html/rfc4315#section-3)), or `None` if the server doesn't
+    /// support UIDPLUS.
+    pub fn copy(&mut self, sequence_set: &str, mailbox_name: &str) -> Result<Option<CopyUid>> {
+        self.run_command_and_read_response(&format!(
+            "COPY {} {}",
+            sequence_set,
+            validate_mailbox(mailbox_name)?
+        ))
+        .and_then(|lines| parse_copy_uid(&lines[..], &mut self.unsolicited_responses_tx))
     }
 
-    pub fn uid_copy(&mut self, uid_set: &str, mailbox_name: &str) -> Result<()> {
-        self.run_command_and_check_ok(&format!("UID COPY {} {}", uid_set, mailbox_name))
+    /// Like [`copy`](#method.copy), but addresses the messages by UID rather than sequence
+    /// number.
+    pub fn uid_copy(&mut self, uid_set: &str, mailbox_name: &str) -> Result<Option<CopyUid>> {
+        self.run_command_and_read_response(&format!(
+            "UID COPY {} {}",
+            uid_set,
+            validate_mailbox(mailbox_name)?
+        ))
+        .and_then(|lines| parse_copy_uid(&lines[..], &mut self.unsolicited_responses_tx))
     }
 
     /// Moves each message in the sequence into the destination mailbox. This function is
     /// named `mv` instead of `move` due to it being a reserved keyword.
     /// The MOVE command is defined in [RFC 6851 - "Internet Message Access Protocol (IMAP)
     /// - MOVE Extension"](https://tools.ietf.org/html/rfc6851#section-3).
-    pub fn mv(&mut self, sequence_set: &str, mailbox_name: &str) -> Result<()> {
-        self.run_command_and_check_ok(&format!(
+    ///
+    /// Returns the source→destination UID mapping reported via the `COPYUID` response code ([RFC
+    /// 4315](https://tools.ietf.org/html/rfc4315#section-3)), or `None` if the server doesn't
+    /// support UIDPLUS.
+    pub fn mv(&mut self, sequence_set: &str, mailbox_name: &str) -> Result<Option<CopyUid>> {
+        self.run_command_and_read_response(&format!(
             "MOVE {} {}",
             sequence_set,
-            validate_str(mailbox_name)?
+            validate_mailbox(mailbox_name)?
         ))
+        .and_then(|lines| parse_copy_uid(&lines[..], &mut self.unsolicited_responses_tx))
     }
 
     /// Moves each message in the uid set into the destination mailbox.
     /// The UID MOVE command is defined in [RFC 6851 - "Internet Message Access Protocol (IMAP)
     /// - MOVE Extension"](https://tools.ietf.org/html/rfc6851#section-3).
-    pub fn uid_mv(&mut self, uid_set: &str, mailbox_name: &str) -> Result<()> {
-        self.run_command_and_check_ok(&format!(
+    ///
+    /// Returns the source→destination UID mapping reported via the `COPYUID` response code ([RFC
+    /// 4315](https://tools.ietf.org/html/rfc4315#section-3)), or `None` if the server doesn't
+    /// support UIDPLUS.
+    pub fn uid_mv(&mut self, uid_set: &str, mailbox_name: &str) -> Result<Option<CopyUid>> {
+        self.run_command_and_read_response(&format!(
             "UID MOVE {} {}",
             uid_set,
-            validate_str(mailbox_name)?
+            validate_mailbox(mailbox_name)?
         ))
+        .and_then(|lines| parse_copy_uid(&lines[..], &mut self.unsolicited_responses_tx))
     }
 
     /// The LIST command returns a subset of names from the complete set
@@ -623,10 +1165,10 @@ impl<T: Read + Write> Session<T> {
     ) -> ZeroCopyResult<Vec<Name>> {
         self.run_command_and_read_response(&format!(
             "LIST {} {}",
-            quote!(reference_name),
-            mailbox_search_pattern
+            quote!(mutf7::encode(reference_name)),
+            mutf7::encode(mailbox_search_pattern)
         ))
-        .and_then(|lines| parse_names(lines, &mut self.unsolicited_responses_tx))
+        .and_then(|lines| parse_names(&lines[..], &mut self.unsolicited_responses_tx))
     }
 
     /// The LSUB command returns a subset of names from the set of names
@@ -638,17 +1180,17 @@ impl<T: Read + Write> Session<T> {
     ) -> ZeroCopyResult<Vec<Name>> {
         self.run_command_and_read_response(&format!(
             "LSUB {} {}",
-            quote!(reference_name),
-            mailbox_search_pattern
+            quote!(mutf7::encode(reference_name)),
+            mutf7::encode(mailbox_search_pattern)
         ))
-        .and_then(|lines| parse_names(lines, &mut self.unsolicited_responses_tx))
+        .and_then(|lines| parse_names(&lines[..], &mut self.unsolicited_responses_tx))
     }
 
     /// The STATUS command requests the status of the indicated mailbox.
     pub fn status(&mut self, mailbox_name: &str, status_data_items: &str) -> Result<Mailbox> {
         self.run_command_and_read_response(&format!(
             "STATUS {} {}",
-            validate_str(mailbox_name)?,
+            validate_mailbox(mailbox_name)?,
             status_data_items
         ))
         .and_then(|lines| parse_mailbox(&lines[..], &mut self.unsolicited_responses_tx))
@@ -660,32 +1202,52 @@ impl<T: Read + Write> Session<T> {
         IdleHandle::new(self)
     }
 
-    /// The APPEND command adds a mail to a mailbox.
-    pub fn append(&mut self, folder: &str, content: &[u8]) -> Result<()> {
-        self.run_command(&format!("APPEND \"{}\" {{{}}}", folder, content.len()))?;
-        let mut v = Vec::new();
-        self.readline(&mut v)?;
-        if !v.starts_with(b"+") {
-            return Err(Error::Append);
-        }
-        self.stream.write_all(content)?;
-        self.stream.write_all(b"\r\n")?;
-        self.stream.flush()?;
-        self.read_response().map(|_| ())
+    /// The APPEND command adds a mail to a mailbox. Returns a builder that can be used to attach
+    /// flags and an internal date to the message before sending it with
+    /// [`finish`](struct.Append.html#method.finish).
+    ///
+    /// If `UTF8=ACCEPT` has been turned on via [`enable`](#method.enable), the message is sent
+    /// using the `UTF8 (...)` form of `APPEND` from [RFC
+    /// 6855](https://tools.ietf.org/html/rfc6855#section-4) instead of a bare literal. Either
+    /// way, the literal itself is sent as a non-synchronizing `LITERAL+`/`LITERAL-` literal (RFC
+    /// 7888) when the capabilities fetched by the last call to
+    /// [`capabilities`](#method.capabilities) advertise support for it, saving a round-trip; if
+    /// `capabilities` has not been called on this connection, a synchronizing literal is used.
+    pub fn append<'a>(&'a mut self, folder: &'a str, content: &'a [u8]) -> Append<'a, T> {
+        Append::new(self, folder, content)
     }
 
     /// Searches the mailbox for messages that match the given criteria and returns
     /// the list of message sequence numbers of those messages.
     pub fn search(&mut self, query: &str) -> Result<HashSet<u32>> {
         self.run_command_and_read_response(&format!("SEARCH {}", query))
-            .and_then(|lines| parse_ids(lines, &mut self.unsolicited_responses_tx))
+            .and_then(|lines| parse_ids(&lines[..], &mut self.unsolicited_responses_tx))
     }
 
     /// Searches the mailbox for messages that match the given criteria and returns
     /// the list of unique identifier numbers of those messages.
     pub fn uid_search(&mut self, query: &str) -> Result<HashSet<u32>> {
         self.run_command_and_read_response(&format!("UID SEARCH {}", query))
-            .and_then(|lines| parse_ids(lines, &mut self.unsolicited_responses_tx))
+            .and_then(|lines| parse_ids(&lines[..], &mut self.unsolicited_responses_tx))
+    }
+
+    /// Like [`search`](#method.search), but requests the given RFC 4731 return options (e.g.
+    /// `"MIN MAX COUNT"`) instead of the full set of matching message sequence numbers, so a
+    /// large mailbox doesn't have to transfer every matching id just to answer e.g. "how many
+    /// messages match" or "what's the newest matching message".
+    pub fn search_return(&mut self, return_opts: &str, query: &str) -> Result<SearchResult> {
+        self.run_command_and_read_response(&format!("SEARCH RETURN ({}) {}", return_opts, query))
+            .and_then(|lines| parse_search_return(&lines[..], &mut self.unsolicited_responses_tx))
+    }
+
+    /// Like [`uid_search`](#method.uid_search), but requests the given RFC 4731 return options
+    /// (e.g. `"MIN MAX COUNT"`) instead of the full set of matching UIDs.
+    pub fn uid_search_return(&mut self, return_opts: &str, query: &str) -> Result<SearchResult> {
+        self.run_command_and_read_response(&format!(
+            "UID SEARCH RETURN ({}) {}",
+            return_opts, query
+        ))
+        .and_then(|lines| parse_search_return(&lines[..], &mut self.unsolicited_responses_tx))
     }
 
     // these are only here because they are public interface, the rest is in `Connection`
@@ -710,7 +1272,57 @@ impl<T: Read + Write> Session<T> {
     }
 }
 
-impl<T: Read + Write> Connection<T> {
+impl<T: Read + Write + SetReadTimeout> Connection<T> {
+    /// Sets the read timeout applied to every blocking read a command makes, so that a server
+    /// that stops responding mid-command fails the command with `Error::Timeout` instead of
+    /// hanging forever. `None` disables the timeout. Defaults to roughly 28 minutes.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.stream.get_mut().set_read_timeout(timeout)?;
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    /// The extensions the server has confirmed enabling via a prior call to
+    /// [`Session::enable`](struct.Session.html#method.enable).
+    pub fn enabled(&self) -> &Capabilities {
+        &self.enabled
+    }
+
+    // Sends a command built from `prefix` followed by a literal specifier for `content`, then
+    // streams `content` itself, so any command that needs to send one literal can emit it
+    // uniformly. Prefers the non-synchronizing `{n+}` form (`LITERAL+`, RFC 7888) when the
+    // server's capabilities (as of the last call to `capabilities()`) advertise it, or when they
+    // advertise the more limited `LITERAL-` and `content` is within its 4096-byte cap; otherwise
+    // falls back to a synchronizing literal, waiting for the server's `+` continuation before
+    // writing `content`. `literal8` selects the `~{n}` form RFC 6855's `UTF8 (...)` `APPEND`
+    // needs instead of a plain `{n}`.
+    fn write_command_with_literal(
+        &mut self,
+        prefix: &str,
+        content: &[u8],
+        literal8: bool,
+    ) -> Result<()> {
+        let non_sync = self.capabilities.as_ref().map_or(false, |caps| {
+            caps.has("LITERAL+") || (caps.has("LITERAL-") && content.len() <= 4096)
+        });
+        let tilde = if literal8 { "~" } else { "" };
+        let spec = if non_sync {
+            format!("{}{{{}+}}", tilde, content.len())
+        } else {
+            format!("{}{{{}}}", tilde, content.len())
+        };
+        self.run_command(&format!("{}{}", prefix, spec))?;
+        if !non_sync {
+            let mut v = Vec::new();
+            self.readline(&mut v)?;
+            if !v.starts_with(b"+") {
+                return Err(Error::Append);
+            }
+        }
+        self.stream.write_all(content)?;
+        Ok(())
+    }
+
     fn read_greeting(&mut self) -> Result<()> {
         let mut v = Vec::new();
         self.readline(&mut v)?;
@@ -738,6 +1350,8 @@ impl<T: Read + Write> Connection<T> {
     }
 
     fn read_response_onto(&mut self, data: &mut Vec<u8>) -> Result<()> {
+        use imap_proto::{parse_response, Response, ResponseCode, Status};
+
         let mut continue_from = None;
         let mut try_first = !data.is_empty();
         let match_tag = format!("{}{}", TAG_PREFIX, self.tag);
@@ -752,7 +1366,6 @@ impl<T: Read + Write> Connection<T> {
             };
 
             let break_with = {
-                use imap_proto::{parse_response, Response, Status};
                 let line = &data[line_start..];
 
                 match parse_response(line) {
@@ -761,17 +1374,20 @@ impl<T: Read + Write> Connection<T> {
                         Response::Done {
                             tag,
                             status,
+                            code,
                             information,
                             ..
                         },
                     )) => {
                         assert_eq!(tag.as_bytes(), match_tag.as_bytes());
                         Some(match status {
-                            Status::Bad | Status::No => {
-                                Err((status, information.map(|s| s.to_string())))
-                            }
-                            Status::Ok => Ok(()),
-                            status => Err((status, None)),
+                            Status::Bad | Status::No => Err((
+                                status,
+                                code.map(super::error::ResponseCode::from_imap_proto),
+                                information.map(|s| s.to_string()),
+                            )),
+                            Status::Ok => Ok(code),
+                            status => Err((status, None, None)),
                         })
                     }
                     Ok((..)) => None,
@@ -779,25 +1395,70 @@ impl<T: Read + Write> Connection<T> {
                         continue_from = Some(line_start);
                         None
                     }
-                    _ => Some(Err((Status::Bye, None))),
+                    _ => Some(Err((Status::Bye, None, None))),
                 }
             };
 
             match break_with {
-                Some(Ok(_)) => {
+                // The tagged completion line itself is dropped from `data` once we're done with
+                // it, but a `[READ-ONLY]`/`[READ-WRITE]` code on it (as returned by `SELECT`/
+                // `EXAMINE`) is the only place that information appears, so turn it into a
+                // synthetic untagged `OK` response `parse_mailbox` already knows how to read.
+                Some(Ok(code)) => {
                     data.truncate(line_start);
+                    match code {
+                        Some(ResponseCode::ReadOnly) => {
+                            data.extend_from_slice(b"* OK [READ-ONLY]\r\n")
+                        }
+                        Some(ResponseCode::ReadWrite) => {
+                            data.extend_from_slice(b"* OK [READ-WRITE]\r\n")
+                        }
+                        // A conditional `STORE`/`UID STORE` with `UNCHANGEDSINCE` reports the UIDs
+                        // that failed the precondition this way, even though the command as a
+                        // whole still completed `OK`. See RFC 7162, section 3.1.3.
+                        Some(ResponseCode::Modified(uids)) => {
+                            break Err(Error::Modified(uids.into_iter().collect()));
+                        }
+                        // `APPEND` reports the UID it assigned the new message this way; turn it
+                        // into a synthetic untagged `OK` response so `parse_append_uid` can read
+                        // it back out of the data `APPEND` returns. See RFC 4315, section 3.
+                        Some(ResponseCode::AppendUid(uid_validity, uid)) => {
+                            if let imap_proto::types::UidSetMember::Uid(uid) = uid {
+                                data.extend_from_slice(
+                                    format!("* OK [APPENDUID {} {}]\r\n", uid_validity, uid)
+                                        .as_bytes(),
+                                );
+                            }
+                        }
+                        // `COPY`/`UID COPY`/`MOVE`/`UID MOVE` report the source->destination UID
+                        // mapping this way; turn it into a synthetic untagged `OK` response so
+                        // `parse_copy_uid` can read it back out. See RFC 4315, section 3.
+                        Some(ResponseCode::CopyUid(uid_validity, source, dest)) => {
+                            data.extend_from_slice(
+                                format!(
+                                    "* OK [COPYUID {} {} {}]\r\n",
+                                    uid_validity,
+                                    uid_set(&source),
+                                    uid_set(&dest)
+                                )
+                                .as_bytes(),
+                            );
+                        }
+                        _ => {}
+                    }
                     break Ok(());
                 }
-                Some(Err((status, expl))) => {
-                    use imap_proto::Status;
+                Some(Err((status, code, expl))) => {
                     match status {
                         Status::Bad => {
                             break Err(Error::BadResponse(
+                                code,
                                 expl.unwrap_or_else(|| "no explanation given".to_string()),
                             ))
                         }
                         Status::No => {
                             break Err(Error::NoResponse(
+                                code,
                                 expl.unwrap_or_else(|| "no explanation given".to_string()),
                             ))
                         }
@@ -809,9 +1470,16 @@ impl<T: Read + Write> Connection<T> {
         }
     }
 
+    // Reads a single CRLF-terminated line into `into`. If the line ends in a literal specifier
+    // (e.g. `{1234}` or the non-synchronizing `{1234+}`, per [RFC 3501 section
+    // 4.3](https://tools.ietf.org/html/rfc3501#section-4.3)), the literal's bytes are read
+    // directly afterwards in one bounded read and appended to `into` as well, so that a message
+    // body containing raw CR/LF bytes is pulled off the wire as a single chunk rather than being
+    // split wherever one of those bytes happens to look like a line ending.
     fn readline(&mut self, into: &mut Vec<u8>) -> Result<usize> {
         use std::io::BufRead;
-        let read = self.stream.read_until(LF, into)?;
+        let start = into.len();
+        let read = self.stream.read_until(LF, into).map_err(classify_io_err)?;
         if read == 0 {
             return Err(Error::ConnectionLost);
         }
@@ -823,7 +1491,26 @@ impl<T: Read + Write> Connection<T> {
             print!("S: {}\n", String::from_utf8_lossy(line));
         }
 
-        Ok(read)
+        if let Some(n) = pending_literal_len(&into[start..]) {
+            self.read_literal(into, n)?;
+        }
+
+        Ok(into.len() - start)
+    }
+
+    // Reads exactly `n` bytes of literal data straight from the stream and appends them to
+    // `into`, bypassing line buffering entirely so the read is bounded by the literal's
+    // advertised length instead of by where the next `\n` byte happens to fall.
+    fn read_literal(&mut self, into: &mut Vec<u8>, n: usize) -> Result<()> {
+        let start = into.len();
+        into.resize(start + n, 0);
+        self.stream
+            .read_exact(&mut into[start..])
+            .map_err(classify_io_err)?;
+        if self.debug {
+            print!("S: {{{} bytes of literal data}}\n", n);
+        }
+        Ok(())
     }
 
     fn create_command(&mut self, command: &str) -> String {
@@ -854,6 +1541,26 @@ mod tests {
         };
     }
 
+    #[test]
+    fn pending_literal_len_test() {
+        assert_eq!(
+            pending_literal_len(b"* 2 FETCH (BODY[TEXT] {3}\r\n"),
+            Some(3)
+        );
+        assert_eq!(
+            pending_literal_len(b"* 2 FETCH (BODY[TEXT] {3+}\r\n"),
+            Some(3)
+        );
+        assert_eq!(pending_literal_len(b"a1 OK done\r\n"), None);
+        // Free-form resp-text can legitimately end in something that looks like a literal
+        // specifier; since the line is already a syntactically complete response, it must not
+        // be misread as introducing one.
+        assert_eq!(
+            pending_literal_len(b"a1 OK [ALERT] quota exceeded {1000}\r\n"),
+            None
+        );
+    }
+
     #[test]
     fn read_response() {
         let response = "a0 OK Logged in.\r\n";
@@ -874,6 +1581,38 @@ mod tests {
         session.read_response().unwrap();
     }
 
+    #[test]
+    fn fetch_body_with_embedded_newline() {
+        // The literal's 5 bytes include a raw `\n`, which must be read as part of the literal
+        // rather than treated as a line ending.
+        let response = b"a0 OK Logged in.\r\n\
+                        * 2 FETCH (BODY[TEXT] {5}\r\nfo\no)\r\n\
+                        a0 OK FETCH completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        session.read_response().unwrap();
+        session.read_response().unwrap();
+    }
+
+    #[test]
+    fn fetch_body_literal_across_fragmented_reads() {
+        // `with_delay` forces the very first underlying `read` to return just one byte, so the
+        // response arrives over more than one `read` call instead of one contiguous read (the
+        // first line is split; the rest, literal included, follows in the next read). The
+        // literal read by `read_literal` should come out identical to a single contiguous read.
+        let response = b"a0 OK Logged in.\r\n\
+                        * 2 FETCH (BODY[TEXT] {3}\r\nfoo)\r\n\
+                        a0 OK FETCH completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response).with_delay();
+        let mut session = mock_session!(mock_stream);
+        session.read_response().unwrap();
+        let data = session.read_response().unwrap();
+        let fetches = parse_fetches(&data[..], &mut session.unsolicited_responses_tx).unwrap();
+        assert_eq!(fetches[0].text(), Some(&b"foo"[..]));
+    }
+
     #[test]
     fn read_greeting() {
         let greeting = "* OK Dovecot ready.\r\n";
@@ -948,8 +1687,8 @@ mod tests {
         let client = Client::new(mock_stream);
         enum Authenticate { Auth };
         impl Authenticator for Authenticate {
-            fn process(&self, _: String) -> String {
-                "foo".to_string()
+            fn process(&mut self, _: &[u8]) -> Vec<u8> {
+                b"foo".to_vec()
             }
         }
         let auth = Authenticate::Auth;
@@ -1102,6 +1841,8 @@ mod tests {
             permanent_flags: vec![],
             uid_next: Some(2),
             uid_validity: Some(1257842737),
+            highest_mod_seq: None,
+            read_only: true,
         };
         let mailbox_name = "INBOX";
         let command = format!("a1 EXAMINE {}\r\n", quote!(mailbox_name));
@@ -1148,6 +1889,8 @@ mod tests {
             ],
             uid_next: Some(2),
             uid_validity: Some(1257842737),
+            highest_mod_seq: None,
+            read_only: true,
         };
         let mailbox_name = "INBOX";
         let command = format!("a1 SELECT {}\r\n", quote!(mailbox_name));
@@ -1161,6 +1904,85 @@ mod tests {
         assert_eq!(mailbox, expected_mailbox);
     }
 
+    #[test]
+    fn select_read_write() {
+        let response = b"* FLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft)\r\n\
+            * 1 EXISTS\r\n\
+            * 1 RECENT\r\n\
+            a1 OK [READ-WRITE] Select completed.\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        let mailbox = session.select("INBOX").unwrap();
+        assert!(!mailbox.read_only);
+    }
+
+    #[test]
+    fn select_no_with_trycreate_code() {
+        let response = b"a1 NO [TRYCREATE] No such mailbox\r\n".to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        match session.select("NoSuchMailbox").unwrap_err() {
+            Error::NoResponse(Some(super::super::error::ResponseCode::TryCreate), msg) => {
+                assert_eq!(msg, "No such mailbox");
+            }
+            e => panic!("Unexpected response: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn select_condstore() {
+        let response = b"* FLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft)\r\n\
+            * 1 EXISTS\r\n\
+            * 1 RECENT\r\n\
+            * OK [HIGHESTMODSEQ 7] \r\n\
+            a1 OK [READ-WRITE] Select completed.\r\n"
+            .to_vec();
+        let mailbox_name = "INBOX";
+        let command = format!("a1 SELECT {} (CONDSTORE)\r\n", quote!(mailbox_name));
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        let mailbox = session.select_condstore(mailbox_name).unwrap();
+        assert!(
+            session.stream.get_ref().written_buf == command.as_bytes().to_vec(),
+            "Invalid select command"
+        );
+        assert_eq!(mailbox.highest_mod_seq, Some(7));
+    }
+
+    #[test]
+    fn select_qresync() {
+        let response = b"* FLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft)\r\n\
+            * 1 EXISTS\r\n\
+            * 1 RECENT\r\n\
+            * VANISHED (EARLIER) 1:3\r\n\
+            * 4 FETCH (FLAGS (\\Seen))\r\n\
+            a1 OK [READ-WRITE] Select completed.\r\n"
+            .to_vec();
+        let mailbox_name = "INBOX";
+        let command = format!(
+            "a1 SELECT {} (QRESYNC (1257842737 4 1:10))\r\n",
+            quote!(mailbox_name)
+        );
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        session
+            .select_qresync(mailbox_name, 1257842737, 4, Some("1:10"))
+            .unwrap();
+        assert!(
+            session.stream.get_ref().written_buf == command.as_bytes().to_vec(),
+            "Invalid select command"
+        );
+        assert_eq!(
+            session.unsolicited_responses.try_recv().unwrap(),
+            UnsolicitedResponse::Vanished(vec![1, 2, 3])
+        );
+        match session.unsolicited_responses.try_recv().unwrap() {
+            UnsolicitedResponse::Fetch(f) => assert_eq!(f.message, 4),
+            r => panic!("unexpected unsolicited response: {:?}", r),
+        }
+    }
+
     #[test]
     fn search() {
         let response = b"* SEARCH 1 2 3 4 5\r\n\
@@ -1193,6 +2015,46 @@ mod tests {
         assert_eq!(ids, [1, 2, 3, 4, 5].iter().cloned().collect());
     }
 
+    #[test]
+    fn search_return() {
+        let response = b"* ESEARCH (TAG \"a1\") UID MIN 3 MAX 15 COUNT 4 ALL 3:10,15\r\n\
+            a1 OK Search completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        let result = session.search_return("MIN MAX COUNT ALL", "Unseen").unwrap();
+        assert!(
+            session.stream.get_ref().written_buf
+                == b"a1 SEARCH RETURN (MIN MAX COUNT ALL) Unseen\r\n".to_vec(),
+            "Invalid search command"
+        );
+        assert!(result.uid);
+        assert_eq!(result.min, Some(3));
+        assert_eq!(result.max, Some(15));
+        assert_eq!(result.count, Some(4));
+        assert_eq!(
+            result.all,
+            [3, 4, 5, 6, 7, 8, 9, 10, 15].iter().cloned().collect()
+        );
+    }
+
+    // A server without ESEARCH support replies to `SEARCH RETURN` the same way it would a plain
+    // `SEARCH`; every id it returns should be treated as `ALL`.
+    #[test]
+    fn search_return_legacy_fallback() {
+        let response = b"* SEARCH 1 2 3 4 5\r\n\
+            a1 OK Search completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        let result = session.search_return("ALL", "Unseen").unwrap();
+        assert!(!result.uid);
+        assert_eq!(result.min, None);
+        assert_eq!(result.max, None);
+        assert_eq!(result.count, None);
+        assert_eq!(result.all, [1, 2, 3, 4, 5].iter().cloned().collect());
+    }
+
     #[test]
     fn capability() {
         let response = b"* CAPABILITY IMAP4rev1 STARTTLS AUTH=GSSAPI LOGINDISABLED\r\n\
@@ -1212,6 +2074,78 @@ mod tests {
         }
     }
 
+    #[test]
+    fn enable() {
+        let response = b"* ENABLED CONDSTORE\r\n\
+            a1 OK ENABLE completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        let enabled = session.enable(&["CONDSTORE", "QRESYNC"]).unwrap();
+        assert!(
+            session.stream.get_ref().written_buf == b"a1 ENABLE CONDSTORE QRESYNC\r\n".to_vec(),
+            "Invalid enable command"
+        );
+        assert!(enabled.has("CONDSTORE"));
+        assert!(!enabled.has("QRESYNC"));
+    }
+
+    #[test]
+    fn get_metadata() {
+        let response = b"* METADATA \"INBOX\" (/private/comment \"My comment\")\r\n\
+            a1 OK GETMETADATA completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        let metadata = session
+            .get_metadata("INBOX", Some("DEPTH 1"), &["/private/comment"])
+            .unwrap();
+        assert!(
+            session.stream.get_ref().written_buf
+                == b"a1 GETMETADATA (DEPTH 1) \"INBOX\" (\"/private/comment\")\r\n".to_vec(),
+            "Invalid getmetadata command"
+        );
+        assert_eq!(
+            metadata,
+            vec![(
+                "/private/comment".to_string(),
+                Some(b"My comment".to_vec())
+            )]
+        );
+    }
+
+    #[test]
+    fn get_metadata_nil_value() {
+        // A server that hasn't set an entry reports it back as NIL rather than omitting it.
+        let response = b"* METADATA \"INBOX\" (/private/comment NIL)\r\n\
+            a1 OK GETMETADATA completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        let metadata = session
+            .get_metadata("INBOX", None, &["/private/comment"])
+            .unwrap();
+        assert_eq!(metadata, vec![("/private/comment".to_string(), None)]);
+    }
+
+    #[test]
+    fn set_metadata() {
+        let response = b"a1 OK SETMETADATA completed\r\n".to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        session
+            .set_metadata(
+                "INBOX",
+                &[("/private/comment", Some("My comment")), ("/private/other", None)],
+            )
+            .unwrap();
+        assert!(
+            session.stream.get_ref().written_buf
+                == b"a1 SETMETADATA \"INBOX\" (\"/private/comment\" \"My comment\" \"/private/other\" NIL)\r\n".to_vec(),
+            "Invalid setmetadata command"
+        );
+    }
+
     #[test]
     fn create() {
         let response = b"a1 OK CREATE completed\r\n".to_vec();
@@ -1264,6 +2198,71 @@ mod tests {
         );
     }
 
+    #[test]
+    fn unselect() {
+        let response = b"* CAPABILITY IMAP4rev1 UNSELECT\r\n\
+            a1 OK CAPABILITY completed\r\n\
+            a2 OK UNSELECT completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        session.unselect().unwrap();
+        assert!(
+            session.stream.get_ref().written_buf
+                == b"a1 CAPABILITY\r\na2 UNSELECT\r\n".to_vec(),
+            "Invalid unselect command"
+        );
+    }
+
+    #[test]
+    fn unselect_unsupported() {
+        let response = b"* CAPABILITY IMAP4rev1\r\n\
+            a1 OK CAPABILITY completed\r\n"
+            .to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        match session.unselect() {
+            Err(Error::Unsupported("UNSELECT")) => {}
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn append() {
+        let response = b"+ Ready\r\na1 OK APPEND completed\r\n".to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        let uid = session.append("INBOX", b"hello").finish().unwrap();
+        assert_eq!(uid, None);
+        assert!(
+            session.stream.get_ref().written_buf == b"a1 APPEND \"INBOX\" {5}\r\nhello\r\n".to_vec(),
+            "Invalid append command"
+        );
+    }
+
+    #[test]
+    fn append_with_flags_and_internal_date() {
+        let response =
+            b"+ Ready\r\na1 OK [APPENDUID 1511554416 42] APPEND completed\r\n".to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        let uid = session
+            .append("INBOX", b"hello")
+            .flags(&["\\Seen", "\\Draft"])
+            .internal_date("01-Jan-2024 00:00:00 +0000")
+            .finish()
+            .unwrap()
+            .unwrap();
+        assert_eq!(uid.uid_validity, 1511554416);
+        assert_eq!(uid.uid, 42);
+        assert!(
+            session.stream.get_ref().written_buf
+                == b"a1 APPEND \"INBOX\" (\\Seen \\Draft) \"01-Jan-2024 00:00:00 +0000\" {5}\r\nhello\r\n"
+                    .to_vec(),
+            "Invalid append command"
+        );
+    }
+
     #[test]
     fn store() {
         generic_store(" ", |c, set, query| c.store(set, query));
@@ -1274,6 +2273,33 @@ mod tests {
         generic_store(" UID ", |c, set, query| c.uid_store(set, query));
     }
 
+    #[test]
+    fn store_unchangedsince() {
+        let res = b"* 2 FETCH (FLAGS (\\Deleted \\Seen) MODSEQ (5))\r\n\
+                   a1 OK STORE completed\r\n"
+            .to_vec();
+        let mut session = mock_session!(MockStream::new(res));
+        let fetches = session
+            .store_unchangedsince("2", 4, "+FLAGS (\\Deleted)")
+            .unwrap();
+        assert!(
+            session.stream.get_ref().written_buf
+                == b"a1 STORE 2 (UNCHANGEDSINCE 4) +FLAGS (\\Deleted)\r\n".to_vec(),
+            "Invalid store command"
+        );
+        assert_eq!(fetches[0].mod_seq, Some(5));
+    }
+
+    #[test]
+    fn store_unchangedsince_modified() {
+        let res = b"a1 OK [MODIFIED 2,4] Conditional STORE failed\r\n".to_vec();
+        let mut session = mock_session!(MockStream::new(res));
+        match session.store_unchangedsince("2:4", 4, "+FLAGS (\\Deleted)") {
+            Err(Error::Modified(uids)) => assert_eq!(uids, vec![2, 4]),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
     fn generic_store<F, T>(prefix: &str, op: F)
     where
         F: FnOnce(&mut Session<MockStream>, &str, &str) -> Result<T>,
@@ -1296,17 +2322,54 @@ mod tests {
         generic_copy(" UID ", |c, set, query| c.uid_copy(set, query))
     }
 
+    #[test]
+    fn copy_without_uidplus() {
+        let response = b"a1 OK COPY completed\r\n".to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        assert_eq!(session.copy("2:4", "MEETING").unwrap(), None);
+    }
+
+    #[test]
+    fn copy_uid() {
+        let response =
+            b"* OK [COPYUID 1511554416 142,399 41:42] Copied UIDs.\r\na1 OK COPY completed\r\n"
+                .to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        let copy_uid = session.copy("2:4", "MEETING").unwrap().unwrap();
+        assert_eq!(copy_uid.uid_validity, 1511554416);
+        assert_eq!(copy_uid.uids, vec![(142, 41), (399, 42)]);
+    }
+
+    // Real servers (e.g. Dovecot, Gmail) put `COPYUID` on the tagged `OK` that completes `COPY`,
+    // not on an untagged line of its own; see RFC 4315, section 3.
+    #[test]
+    fn copy_uid_on_tagged_completion() {
+        let response = b"a1 OK [COPYUID 1511554416 142,399 41:42] Copied UIDs.\r\n".to_vec();
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        let copy_uid = session.copy("2:4", "MEETING").unwrap().unwrap();
+        assert_eq!(copy_uid.uid_validity, 1511554416);
+        assert_eq!(copy_uid.uids, vec![(142, 41), (399, 42)]);
+    }
+
+    // Unlike `generic_with_uid` (shared with FETCH/STORE, whose final argument isn't a mailbox
+    // name and so mustn't be quoted), COPY/UID COPY's mailbox name is quoted and modified-UTF-7
+    // encoded on the wire, so this builds its own expected command.
     fn generic_copy<F, T>(prefix: &str, op: F)
     where
         F: FnOnce(&mut Session<MockStream>, &str, &str) -> Result<T>,
     {
-        generic_with_uid(
-            "OK COPY completed\r\n",
-            "COPY",
-            "2:4",
-            "MEETING",
-            prefix,
-            op,
+        let response = b"a1 OK COPY completed\r\n".to_vec();
+        let mailbox_name = "MEETING";
+        let command = format!("a1{}COPY 2:4 {}\r\n", prefix, quote!(mailbox_name));
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        let _ = op(&mut session, "2:4", mailbox_name);
+        assert!(
+            session.stream.get_ref().written_buf == command.as_bytes().to_vec(),
+            "Invalid copy command"
         );
     }
 
@@ -1321,7 +2384,9 @@ mod tests {
         let command = format!("a1 MOVE 1:2 {}\r\n", quote!(mailbox_name));
         let mock_stream = MockStream::new(response);
         let mut session = mock_session!(mock_stream);
-        session.mv("1:2", mailbox_name).unwrap();
+        let copy_uid = session.mv("1:2", mailbox_name).unwrap().unwrap();
+        assert_eq!(copy_uid.uid_validity, 1511554416);
+        assert_eq!(copy_uid.uids, vec![(142, 41), (399, 42)]);
         assert!(
             session.stream.get_ref().written_buf == command.as_bytes().to_vec(),
             "Invalid move command"
@@ -1339,7 +2404,9 @@ mod tests {
         let command = format!("a1 UID MOVE 41:42 {}\r\n", quote!(mailbox_name));
         let mock_stream = MockStream::new(response);
         let mut session = mock_session!(mock_stream);
-        session.uid_mv("41:42", mailbox_name).unwrap();
+        let copy_uid = session.uid_mv("41:42", mailbox_name).unwrap().unwrap();
+        assert_eq!(copy_uid.uid_validity, 1511554416);
+        assert_eq!(copy_uid.uids, vec![(142, 41), (399, 42)]);
         assert!(
             session.stream.get_ref().written_buf == command.as_bytes().to_vec(),
             "Invalid uid move command"
@@ -1363,6 +2430,40 @@ mod tests {
         generic_with_uid("OK FETCH completed\r\n", "FETCH", "1", "BODY[]", prefix, op);
     }
 
+    #[test]
+    fn fetch_changedsince() {
+        generic_fetch_changedsince(" ", |c, seq, mod_seq, query| {
+            c.fetch_changedsince(seq, mod_seq, query)
+        })
+    }
+
+    #[test]
+    fn uid_fetch_changedsince() {
+        generic_fetch_changedsince(" UID ", |c, seq, mod_seq, query| {
+            c.uid_fetch_changedsince(seq, mod_seq, query)
+        })
+    }
+
+    fn generic_fetch_changedsince<F>(prefix: &str, op: F)
+    where
+        F: FnOnce(&mut Session<MockStream>, &str, u64, &str) -> ZeroCopyResult<(Vec<Fetch>, Vec<u32>)>,
+    {
+        let response = b"* 2 FETCH (MODSEQ (5) FLAGS (\\Seen))\r\n\
+            * VANISHED (EARLIER) 3:4\r\n\
+            a1 OK FETCH completed\r\n"
+            .to_vec();
+        let command = format!("a1{}FETCH 1:4 FLAGS (CHANGEDSINCE 4)\r\n", prefix);
+        let mock_stream = MockStream::new(response);
+        let mut session = mock_session!(mock_stream);
+        let (fetches, vanished) = op(&mut session, "1:4", 4, "FLAGS").unwrap();
+        assert!(
+            session.stream.get_ref().written_buf == command.as_bytes().to_vec(),
+            "Invalid fetch command"
+        );
+        assert_eq!(fetches[0].mod_seq, Some(5));
+        assert_eq!(vanished, vec![3, 4]);
+    }
+
     fn generic_with_uid<F, T>(res: &str, cmd: &str, seq: &str, query: &str, prefix: &str, op: F)
     where
         F: FnOnce(&mut Session<MockStream>, &str, &str) -> Result<T>,
@@ -2,12 +2,16 @@ use std::io::Error as IoError;
 use std::result;
 use std::fmt;
 use std::error::Error as StdError;
+#[cfg(feature = "native-tls")]
 use std::net::TcpStream;
 use std::string::FromUtf8Error;
 
+#[cfg(feature = "native-tls")]
 use native_tls::HandshakeError as TlsHandshakeError;
+#[cfg(feature = "native-tls")]
 use native_tls::Error as TlsError;
 use bufstream::IntoInnerError as BufError;
+use imap_proto::Response;
 
 pub type Result<T> = result::Result<T, Error>;
 
@@ -17,19 +21,166 @@ pub enum Error {
     /// An `io::Error` that occurred while trying to read or write to a network stream.
     Io(IoError),
     /// An error from the `native_tls` library during the TLS handshake.
+    #[cfg(feature = "native-tls")]
     TlsHandshake(TlsHandshakeError<TcpStream>),
     /// An error from the `native_tls` library while managing the socket.
+    #[cfg(feature = "native-tls")]
     Tls(TlsError),
-    /// A BAD response from the IMAP server.
-    BadResponse(Vec<String>),
-    /// A NO response from the IMAP server.
-    NoResponse(Vec<String>),
+    /// An error from the `rustls` library during the TLS handshake or connection setup.
+    #[cfg(feature = "rustls-tls")]
+    Rustls(String),
+    /// A BAD response from the IMAP server, together with its resp-text-code, if any.
+    BadResponse(Option<ResponseCode>, String),
+    /// A NO response from the IMAP server, together with its resp-text-code, if any.
+    NoResponse(Option<ResponseCode>, String),
+    /// A conditional `STORE`/`UID STORE` (`UNCHANGEDSINCE`, RFC 7162) failed its precondition for
+    /// some of the given messages; this holds the UIDs of the ones that were *not* updated.
+    Modified(Vec<u32>),
     /// The connection was terminated unexpectedly.
     ConnectionLost,
+    /// A command's blocking read did not complete before the configured read timeout elapsed
+    /// (see `Connection::set_timeout`). The connection is left in a half-read state and should be
+    /// dropped rather than reused.
+    Timeout,
     // Error parsing a server response.
     Parse(ParseError),
     // Error appending a mail
     Append,
+    /// Command inputs were not valid [IMAP
+    /// strings](https://tools.ietf.org/html/rfc3501#section-4.3).
+    Validate(ValidateError),
+    /// The server did not advertise support for a capability a command requires, so the command
+    /// was not sent. Holds the name of the missing capability.
+    Unsupported(&'static str),
+}
+
+/// An error occurred while trying to validate a string given to a command.
+#[derive(Debug)]
+pub struct ValidateError(pub(crate) char);
+
+impl fmt::Display for ValidateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid character in input: '{}'",
+            self.0.escape_default()
+        )
+    }
+}
+
+impl StdError for ValidateError {
+    fn description(&self) -> &str {
+        "Invalid character in input"
+    }
+}
+
+/// The resp-text-code RFC 3501 attaches to a tagged completion ([RFC 3501 section
+/// 7.1](https://tools.ietf.org/html/rfc3501#section-7.1)): a bracketed atom, with optional
+/// arguments, giving programmatic detail beyond the accompanying human-readable text. Carried on
+/// [`Error::BadResponse`]/[`Error::NoResponse`] so callers can react to it directly instead of
+/// pattern-matching the text, e.g. auto-creating a mailbox on `TryCreate` or surfacing `Alert`
+/// text to the user.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum ResponseCode {
+    /// `[ALERT]`: the accompanying text should be presented to the user.
+    Alert,
+    /// `[BADCHARSET]`, optionally naming the charsets the server does support.
+    BadCharset(Vec<String>),
+    /// `[CAPABILITY ...]`: the server's capability list, as also returned by `CAPABILITY`.
+    Capability(Vec<String>),
+    /// `[PARSE]`: the server couldn't parse the headers of a message in the mailbox.
+    Parse,
+    /// `[PERMANENTFLAGS (...)]`: the flags the client can set permanently.
+    PermanentFlags(Vec<String>),
+    /// `[READ-ONLY]`: the mailbox is selected read-only.
+    ReadOnly,
+    /// `[READ-WRITE]`: the mailbox is selected read-write.
+    ReadWrite,
+    /// `[TRYCREATE]`: the destination mailbox doesn't exist; the client may offer to create it
+    /// and retry the command.
+    TryCreate,
+    /// `[UIDNEXT n]`: the next UID value the mailbox will assign.
+    UidNext(u32),
+    /// `[UIDVALIDITY n]`: the mailbox's UID validity value.
+    UidValidity(u32),
+    /// `[UNSEEN n]`: the sequence number of the first unseen message.
+    Unseen(u32),
+    /// Any other response code. Covers codes this crate doesn't give a dedicated variant,
+    /// including ones newer than RFC 3501 (e.g. IMAP's own extensions define more of these, such
+    /// as `UIDPLUS`'s `APPENDUID`/`COPYUID`, which are instead surfaced directly on `Ok`
+    /// responses that carry useful data). The first field is the `{:?}` rendering of
+    /// `imap_proto`'s parsed code, not the bare atom; the arguments are not split out, and the
+    /// second field is always `None`.
+    Other(String, Option<String>),
+}
+
+impl ResponseCode {
+    pub(crate) fn from_imap_proto(code: imap_proto::ResponseCode) -> ResponseCode {
+        use imap_proto::ResponseCode as Raw;
+        match code {
+            Raw::Alert => ResponseCode::Alert,
+            Raw::BadCharset(charsets) => {
+                ResponseCode::BadCharset(charsets.into_iter().map(|s| s.to_string()).collect())
+            }
+            Raw::Capability(caps) => {
+                ResponseCode::Capability(caps.into_iter().map(|s| s.to_string()).collect())
+            }
+            Raw::Parse => ResponseCode::Parse,
+            Raw::PermanentFlags(flags) => {
+                ResponseCode::PermanentFlags(flags.into_iter().map(|s| s.to_string()).collect())
+            }
+            Raw::ReadOnly => ResponseCode::ReadOnly,
+            Raw::ReadWrite => ResponseCode::ReadWrite,
+            Raw::TryCreate => ResponseCode::TryCreate,
+            Raw::UidNext(n) => ResponseCode::UidNext(n),
+            Raw::UidValidity(n) => ResponseCode::UidValidity(n),
+            Raw::Unseen(n) => ResponseCode::Unseen(n),
+            other => ResponseCode::Other(format!("{:?}", other), None),
+        }
+    }
+}
+
+impl Error {
+    /// Whether this error reflects a dead or broken underlying connection, as opposed to one the
+    /// server returned in response to a specific command. Transient errors are worth tearing down
+    /// the stream and reconnecting for, since retrying the same command on the same connection
+    /// would just fail the same way; non-transient ones (a `NO`/`BAD` response, a parse failure,
+    /// ...) won't be fixed by reconnecting. See [`ReconnectSession`](../reconnect/index.html).
+    pub fn is_transient(&self) -> bool {
+        match *self {
+            Error::Io(_) | Error::ConnectionLost => true,
+            #[cfg(feature = "native-tls")]
+            Error::Tls(_) | Error::TlsHandshake(_) => true,
+            #[cfg(feature = "rustls-tls")]
+            Error::Rustls(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<'a> From<Response<'a>> for Error {
+    fn from(response: Response<'a>) -> Error {
+        use imap_proto::Status;
+        match response {
+            Response::Done {
+                status,
+                code,
+                information,
+                ..
+            } => {
+                let code = code.map(ResponseCode::from_imap_proto);
+                let msg = information
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| "no explanation given".to_string());
+                match status {
+                    Status::Bad => Error::BadResponse(code, msg),
+                    Status::No => Error::NoResponse(code, msg),
+                    _ => Error::Parse(ParseError::Invalid(msg.into_bytes())),
+                }
+            }
+            resp => Error::Parse(ParseError::Invalid(format!("{:?}", resp).into_bytes())),
+        }
+    }
 }
 
 impl From<IoError> for Error {
@@ -44,12 +195,14 @@ impl<T> From<BufError<T>> for Error {
     }
 }
 
+#[cfg(feature = "native-tls")]
 impl From<TlsHandshakeError<TcpStream>> for Error {
     fn from(err: TlsHandshakeError<TcpStream>) -> Error {
         Error::TlsHandshake(err)
     }
 }
 
+#[cfg(feature = "native-tls")]
 impl From<TlsError> for Error {
     fn from(err: TlsError) -> Error {
         Error::Tls(err)
@@ -62,13 +215,40 @@ impl From<FromUtf8Error> for Error {
     }
 }
 
+impl From<base64::DecodeError> for Error {
+    fn from(err: base64::DecodeError) -> Error {
+        Error::Parse(ParseError::Base64Decode(err))
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::Io(ref e) => fmt::Display::fmt(e, f),
+            #[cfg(feature = "native-tls")]
             Error::Tls(ref e) => fmt::Display::fmt(e, f),
+            #[cfg(feature = "native-tls")]
             Error::TlsHandshake(ref e) => fmt::Display::fmt(e, f),
-            ref e => f.write_str(e.description()),
+            #[cfg(feature = "rustls-tls")]
+            Error::Rustls(ref msg) => f.write_str(msg),
+            Error::BadResponse(_, ref msg) => write!(f, "Bad Response: {}", msg),
+            Error::NoResponse(_, ref msg) => write!(f, "No Response: {}", msg),
+            Error::Modified(ref uids) => write!(
+                f,
+                "Conditional STORE precondition failed for UIDs: {}",
+                uids.iter()
+                    .map(|u| u.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            Error::Parse(ref e) => fmt::Display::fmt(e, f),
+            Error::Validate(ref e) => fmt::Display::fmt(e, f),
+            Error::Unsupported(cap) => {
+                write!(f, "Server does not support the required capability: {}", cap)
+            }
+            Error::ConnectionLost => f.write_str("Connection lost"),
+            Error::Timeout => f.write_str("Command timed out"),
+            Error::Append => f.write_str("Could not append mail to mailbox"),
         }
     }
 }
@@ -77,22 +257,33 @@ impl StdError for Error {
     fn description(&self) -> &str {
         match *self {
             Error::Io(ref e) => e.description(),
+            #[cfg(feature = "native-tls")]
             Error::Tls(ref e) => e.description(),
+            #[cfg(feature = "native-tls")]
             Error::TlsHandshake(ref e) => e.description(),
+            #[cfg(feature = "rustls-tls")]
+            Error::Rustls(_) => "Error performing TLS handshake",
             Error::Parse(ref e) => e.description(),
-            Error::BadResponse(_) => "Bad Response",
-            Error::NoResponse(_) => "No Response",
+            Error::BadResponse(_, _) => "Bad Response",
+            Error::NoResponse(_, _) => "No Response",
+            Error::Modified(_) => "Conditional STORE precondition failed for some messages",
             Error::ConnectionLost => "Connection lost",
+            Error::Timeout => "Command timed out",
             Error::Append => "Could not append mail to mailbox",
+            Error::Validate(_) => "Invalid input to command",
+            Error::Unsupported(_) => "Server does not support the required capability",
         }
     }
 
     fn cause(&self) -> Option<&StdError> {
         match *self {
             Error::Io(ref e) => Some(e),
+            #[cfg(feature = "native-tls")]
             Error::Tls(ref e) => Some(e),
+            #[cfg(feature = "native-tls")]
             Error::TlsHandshake(ref e) => Some(e),
-            Error::Parse(ParseError::DataNotUtf8(ref e)) => Some(e),
+            Error::Parse(ref e) => Some(e),
+            Error::Validate(ref e) => Some(e),
             _ => None,
         }
     }
@@ -111,12 +302,35 @@ pub enum ParseError {
     // Authentication errors.
     Authentication(String),
     DataNotUtf8(FromUtf8Error),
+    // The server sent a response that could not be parsed as a known IMAP response.
+    Invalid(Vec<u8>),
+    // A `+ <base64>` continuation from the server (e.g. a SASL challenge) was not valid base64.
+    Base64Decode(base64::DecodeError),
 }
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            ref e => f.write_str(e.description()),
+            ParseError::FromUtf8(ref e) => fmt::Display::fmt(e, f),
+            ParseError::FetchResponse(ref line) => {
+                write!(f, "Unable to parse fetch response: {}", line)
+            }
+            ParseError::StatusResponse(ref lines) => {
+                write!(f, "Unable to parse status response: {}", lines.join("\n"))
+            }
+            ParseError::Capability(ref lines) => {
+                write!(f, "Unable to parse capability response: {}", lines.join("\n"))
+            }
+            ParseError::Authentication(ref line) => {
+                write!(f, "Unable to parse authentication response: {}", line)
+            }
+            ParseError::DataNotUtf8(ref e) => fmt::Display::fmt(e, f),
+            ParseError::Invalid(ref data) => write!(
+                f,
+                "Unable to parse server response: {}",
+                String::from_utf8_lossy(data)
+            ),
+            ParseError::Base64Decode(ref e) => fmt::Display::fmt(e, f),
         }
     }
 }
@@ -130,12 +344,16 @@ impl StdError for ParseError {
             ParseError::Capability(_) => "Unable to parse capability response",
             ParseError::Authentication(_) => "Unable to parse authentication response",
             ParseError::DataNotUtf8(_) => "Unable to parse data as UTF-8 text",
+            ParseError::Invalid(_) => "Unable to parse server response",
+            ParseError::Base64Decode(_) => "Unable to decode base64 authentication data",
         }
     }
 
     fn cause(&self) -> Option<&StdError> {
         match self {
             &ParseError::FromUtf8(ref e) => Some(e),
+            &ParseError::DataNotUtf8(ref e) => Some(e),
+            &ParseError::Base64Decode(ref e) => Some(e),
             _ => None,
         }
     }
@@ -3,15 +3,24 @@
 
 //! imap is a IMAP client for Rust.
 
+extern crate base64;
 extern crate bufstream;
+extern crate imap_proto;
+#[cfg(feature = "native-tls")]
 extern crate native_tls;
+extern crate nom;
 extern crate regex;
+#[cfg(feature = "rustls-tls")]
+extern crate rustls;
 
 pub mod authenticator;
 pub mod client;
 pub mod error;
-pub mod mailbox;
+pub mod reconnect;
+pub mod tls;
+pub mod types;
 
+mod mutf7;
 mod parse;
 
 #[cfg(test)]
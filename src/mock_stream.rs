@@ -1,46 +1,72 @@
-use std::io::{Read, Result, Write, Error, ErrorKind};
 use std::cmp::min;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::time::Duration;
 
+use super::client::SetReadTimeout;
+use super::error::Result as ImapResult;
+
+#[derive(Default)]
 pub struct MockStream {
     read_buf: Vec<u8>,
     read_pos: usize,
     pub written_buf: Vec<u8>,
-    err_on_read: bool
+    err_on_read: bool,
+    eof_on_read: bool,
+    delay_read_by_one: bool,
 }
 
 impl MockStream {
     pub fn new(read_buf: Vec<u8>) -> MockStream {
-        MockStream{
-            read_buf: read_buf,
-            read_pos: 0,
-            written_buf: Vec::new(),
-            err_on_read: false
-        }
+        MockStream::default().with_buf(read_buf)
     }
 
     pub fn new_err() -> MockStream {
-        MockStream{
-            read_buf: Vec::new(),
-            read_pos: 0,
-            written_buf: Vec::new(),
-            err_on_read: true
-        }
+        MockStream::default().with_err()
+    }
+
+    pub fn with_buf(mut self, read_buf: Vec<u8>) -> MockStream {
+        self.read_buf = read_buf;
+        self
+    }
+
+    pub fn with_err(mut self) -> MockStream {
+        self.err_on_read = true;
+        self
+    }
+
+    pub fn with_eof(mut self) -> MockStream {
+        self.eof_on_read = true;
+        self
+    }
+
+    /// Causes the first `read` call to only return a single byte, simulating a slow/fragmented
+    /// read that forces the caller to loop to get the rest.
+    pub fn with_delay(mut self) -> MockStream {
+        self.delay_read_by_one = true;
+        self
     }
 }
 
 impl Read for MockStream {
-    fn read(&mut self, buf: &mut[u8]) -> Result<usize> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         if self.err_on_read {
-            return Err(Error::new(ErrorKind::Other, "MockStream Error"))
+            return Err(Error::new(ErrorKind::Other, "MockStream Error"));
+        }
+        if self.eof_on_read {
+            return Ok(0);
         }
         if self.read_pos >= self.read_buf.len() {
-            return Err(Error::new(ErrorKind::UnexpectedEof, "EOF"))
+            return Err(Error::new(ErrorKind::UnexpectedEof, "EOF"));
         }
-        let write_len = min(buf.len(), self.read_buf.len() - self.read_pos);
+        let max_len = if self.delay_read_by_one {
+            self.delay_read_by_one = false;
+            1
+        } else {
+            buf.len()
+        };
+        let write_len = min(max_len, self.read_buf.len() - self.read_pos);
         let max_pos = self.read_pos + write_len;
-        for x in self.read_pos..max_pos {
-            buf[x - self.read_pos] = self.read_buf[x];
-        }
+        buf[..write_len].copy_from_slice(&self.read_buf[self.read_pos..max_pos]);
         self.read_pos += write_len;
         Ok(write_len)
     }
@@ -56,3 +82,11 @@ impl Write for MockStream {
         Ok(())
     }
 }
+
+impl SetReadTimeout for MockStream {
+    // MockStream never actually blocks on I/O, so there's no timeout to apply; this just lets it
+    // satisfy `Session`/`Client`'s bounds for the tests that construct them.
+    fn set_read_timeout(&mut self, _timeout: Option<Duration>) -> ImapResult<()> {
+        Ok(())
+    }
+}
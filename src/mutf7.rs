@@ -0,0 +1,154 @@
+//! Modified UTF-7 encoding of mailbox names, per [RFC 3501 section
+//! 5.1.3](https://tools.ietf.org/html/rfc3501#section-5.1.3).
+//!
+//! This is IMAP's own variant of [UTF-7](https://tools.ietf.org/html/rfc2152): printable ASCII
+//! (0x20-0x7e) is sent literally, except `&`, which is escaped as `&-`; any other run of
+//! characters is UTF-16BE, base64-encoded with the standard alphabet but using `,` in place of
+//! `/`, with padding stripped, and wrapped in `&...-`.
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+,";
+
+/// Encodes `name` into the modified UTF-7 form used on the wire for mailbox names.
+pub(crate) fn encode(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut run = Vec::new();
+    for c in name.chars() {
+        if c == '&' {
+            flush_run(&mut run, &mut out);
+            out.push_str("&-");
+        } else if (' '..='~').contains(&c) {
+            flush_run(&mut run, &mut out);
+            out.push(c);
+        } else {
+            let mut buf = [0u16; 2];
+            run.extend_from_slice(c.encode_utf16(&mut buf));
+        }
+    }
+    flush_run(&mut run, &mut out);
+    out
+}
+
+// Base64-encodes `run` (UTF-16BE code units) into `&...-` and appends it to `out`, then empties
+// `run`. A no-op if `run` is empty, so callers can call this unconditionally between literal runs.
+fn flush_run(run: &mut Vec<u16>, out: &mut String) {
+    if run.is_empty() {
+        return;
+    }
+    let bytes: Vec<u8> = run.drain(..).flat_map(|u| u.to_be_bytes()).collect();
+    out.push('&');
+    for chunk in bytes.chunks(3) {
+        let b0 = u32::from(chunk[0]);
+        let b1 = u32::from(chunk.get(1).copied().unwrap_or(0));
+        let b2 = u32::from(chunk.get(2).copied().unwrap_or(0));
+        let n = (b0 << 16) | (b1 << 8) | b2;
+        let digits = [
+            BASE64_ALPHABET[((n >> 18) & 0x3f) as usize],
+            BASE64_ALPHABET[((n >> 12) & 0x3f) as usize],
+            BASE64_ALPHABET[((n >> 6) & 0x3f) as usize],
+            BASE64_ALPHABET[(n & 0x3f) as usize],
+        ];
+        let used = match chunk.len() {
+            1 => 2,
+            2 => 3,
+            _ => 4,
+        };
+        out.push_str(std::str::from_utf8(&digits[..used]).unwrap());
+    }
+    out.push('-');
+}
+
+/// Decodes `raw`, the modified UTF-7 form a server sent on the wire, back into a Unicode `String`.
+/// A lone `&` followed by `-` decodes to a literal `&`; any base64 run that isn't valid UTF-16 is
+/// decoded lossily, substituting U+FFFD for the invalid parts, rather than failing outright, since
+/// a mailbox name that can't be fully recovered is still more useful to the caller than none at
+/// all.
+pub(crate) fn decode(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let bytes = raw.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'&' {
+            out.push(bytes[i] as char);
+            i += 1;
+            continue;
+        }
+        if bytes.get(i + 1) == Some(&b'-') {
+            out.push('&');
+            i += 2;
+            continue;
+        }
+        let start = i + 1;
+        let end = bytes[start..]
+            .iter()
+            .position(|&b| b == b'-')
+            .map(|p| start + p)
+            .unwrap_or(bytes.len());
+        let units = decode_base64_units(&raw[start..end]);
+        out.extend(char::decode_utf16(units).map(|r| r.unwrap_or('\u{fffd}')));
+        i = if end < bytes.len() { end + 1 } else { end };
+    }
+    out
+}
+
+// Decodes a modified-base64 run (using `,` rather than `/`, and no padding) into UTF-16BE code
+// units.
+fn decode_base64_units(s: &str) -> Vec<u16> {
+    let mut bits: u32 = 0;
+    let mut nbits = 0;
+    let mut bytes = Vec::new();
+    for b in s.bytes() {
+        let v = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' => 62,
+            b',' => 63,
+            _ => continue,
+        };
+        bits = (bits << 6) | u32::from(v);
+        nbits += 6;
+        if nbits >= 8 {
+            nbits -= 8;
+            bytes.push(((bits >> nbits) & 0xff) as u8);
+        }
+    }
+    bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode};
+
+    #[test]
+    fn round_trips_ascii() {
+        assert_eq!(encode("INBOX"), "INBOX");
+        assert_eq!(decode("INBOX"), "INBOX");
+    }
+
+    #[test]
+    fn escapes_ampersand() {
+        assert_eq!(encode("Q&A"), "Q&-A");
+        assert_eq!(decode("Q&-A"), "Q&A");
+    }
+
+    #[test]
+    fn encodes_non_ascii() {
+        // The example from RFC 3501 section 5.1.3.
+        assert_eq!(encode("~peter/mail/\u{53f0}\u{5317}/\u{65e5}\u{672c}\u{8a9e}"), "~peter/mail/&U,BTFw-/&ZeVnLIqe-");
+    }
+
+    #[test]
+    fn decodes_non_ascii() {
+        assert_eq!(decode("~peter/mail/&U,BTFw-/&ZeVnLIqe-"), "~peter/mail/\u{53f0}\u{5317}/\u{65e5}\u{672c}\u{8a9e}");
+    }
+
+    #[test]
+    fn round_trips_non_ascii() {
+        let name = "Entw\u{fc}rfe/\u{53d7}\u{4fe1}\u{7bb1}";
+        assert_eq!(decode(&encode(name)), name);
+    }
+}
@@ -1,39 +1,70 @@
 use regex::Regex;
-use nom::IResult;
-use imap_proto::{self, Response};
+use std::collections::HashSet;
+use std::result;
+use std::sync::mpsc;
+
+use imap_proto::{self, AttributeValue, MailboxDatum, Response};
 
-use super::types::*;
 use super::error::{Error, ParseError, Result};
+use super::types::*;
 
-pub fn parse_authenticate_response(line: String) -> Result<String> {
-    let authenticate_regex = Regex::new("^+(.*)\r\n").unwrap();
+pub fn parse_authenticate_response(line: String) -> Result<Vec<u8>> {
+    let authenticate_regex = Regex::new("^\\+ ?(.*)\r\n").unwrap();
 
     for cap in authenticate_regex.captures_iter(line.as_str()) {
         let data = cap.get(1).map(|x| x.as_str()).unwrap_or("");
-        return Ok(String::from(data));
+        return Ok(base64::decode(data)?);
     }
 
     Err(Error::Parse(ParseError::Authentication(line)))
 }
 
-enum MapOrNot<'a, T: 'a> {
+enum MapOrNot<'a, T> {
     Map(T),
+    Unsolicited(UnsolicitedResponse),
+    Ignore,
     Not(Response<'a>),
 }
 
-fn parse_many<T, F>(mut lines: &[u8], mut map: F) -> Result<Vec<T>>
+/// Turns an untagged response that isn't the kind of response the caller is waiting for into an
+/// `UnsolicitedResponse`, if it's one of the kinds the server can send unprompted; otherwise,
+/// hands the response back unchanged so the caller can treat it as unexpected.
+pub(crate) fn to_unsolicited(resp: Response) -> result::Result<UnsolicitedResponse, Response> {
+    match resp {
+        Response::MailboxData(MailboxDatum::Exists(n)) => Ok(UnsolicitedResponse::Exists(n)),
+        Response::MailboxData(MailboxDatum::Recent(n)) => Ok(UnsolicitedResponse::Recent(n)),
+        Response::Expunge(n) => Ok(UnsolicitedResponse::Expunge(n)),
+        Response::Fetch(num, attrs) => Ok(UnsolicitedResponse::Fetch(fetch_from_attrs(num, attrs))),
+        resp => Err(resp),
+    }
+}
+
+fn parse_many<'a, T, F>(
+    mut lines: &'a [u8],
+    mut map: F,
+    unsolicited_responses_tx: &mut mpsc::Sender<UnsolicitedResponse>,
+) -> Result<Vec<T>>
 where
-    F: FnMut(Response) -> MapOrNot<T>,
+    F: FnMut(Response<'a>) -> MapOrNot<'a, T>,
 {
     let mut things = Vec::new();
     loop {
         match imap_proto::parse_response(lines) {
-            IResult::Done(rest, resp) => {
+            Ok((rest, resp)) => {
                 lines = rest;
 
                 match map(resp) {
                     MapOrNot::Map(t) => things.push(t),
-                    MapOrNot::Not(resp) => break Err(resp.into()),
+                    MapOrNot::Unsolicited(r) => {
+                        unsolicited_responses_tx.send(r).unwrap();
+                    }
+                    MapOrNot::Ignore => {}
+                    MapOrNot::Not(resp) => match to_unsolicited(resp) {
+                        Ok(r) => {
+                            unsolicited_responses_tx.send(r).unwrap();
+                        }
+                        Err(resp) => break Err(resp.into()),
+                    },
                 }
 
                 if lines.is_empty() {
@@ -47,73 +78,506 @@ where
     }
 }
 
-pub fn parse_names(lines: &[u8]) -> Result<Vec<Name>> {
-    use imap_proto::MailboxDatum;
-    parse_many(lines, |resp| match resp {
-        // https://github.com/djc/imap-proto/issues/4
-        Response::MailboxData(MailboxDatum::List(attrs, delim, name)) => MapOrNot::Map(Name {
-            attributes: attrs.into_iter().map(|s| s.to_string()).collect(),
-            delimiter: delim.to_string(),
-            name: name.to_string(),
-        }),
-        resp => MapOrNot::Not(resp),
-    })
-}
-
-pub fn parse_fetches(lines: &[u8]) -> Result<Vec<Fetch>> {
-    parse_many(lines, |resp| match resp {
-        Response::Fetch(num, attrs) => {
-            let mut fetch = Fetch {
-                message: num,
-                flags: vec![],
-                uid: None,
-            };
-
-            for attr in attrs {
-                use imap_proto::AttributeValue;
-                match attr {
-                    AttributeValue::Flags(flags) => {
-                        fetch.flags.extend(flags.into_iter().map(|s| s.to_string()))
-                    }
-                    AttributeValue::Uid(uid) => fetch.uid = Some(uid),
-                    _ => {}
+pub fn parse_names(
+    lines: &[u8],
+    unsolicited_responses_tx: &mut mpsc::Sender<UnsolicitedResponse>,
+) -> ZeroCopyResult<Vec<Name>> {
+    parse_many(
+        lines,
+        |resp| match resp {
+            // https://github.com/djc/imap-proto/issues/4
+            Response::MailboxData(MailboxDatum::List(attrs, delim, name)) => MapOrNot::Map(Name {
+                attributes: attrs.into_iter().map(|s| s.to_string()).collect(),
+                delimiter: delim.to_string(),
+                raw_name: name.to_string(),
+            }),
+            resp => MapOrNot::Not(resp),
+        },
+        unsolicited_responses_tx,
+    )
+}
+
+pub fn parse_fetches(
+    lines: &[u8],
+    unsolicited_responses_tx: &mut mpsc::Sender<UnsolicitedResponse>,
+) -> ZeroCopyResult<Vec<Fetch>> {
+    parse_many(
+        lines,
+        |resp| match resp {
+            Response::Fetch(num, attrs) => MapOrNot::Map(fetch_from_attrs(num, attrs)),
+            resp => MapOrNot::Not(resp),
+        },
+        unsolicited_responses_tx,
+    )
+}
+
+pub(crate) fn fetch_from_attrs(num: u32, attrs: Vec<AttributeValue>) -> Fetch {
+    let mut fetch = Fetch {
+        message: num,
+        ..Fetch::default()
+    };
+
+    for attr in attrs {
+        match attr {
+            AttributeValue::Flags(flags) => fetch
+                .flags
+                .extend(flags.into_iter().map(|s| s.to_string())),
+            AttributeValue::Uid(uid) => fetch.uid = Some(uid),
+            AttributeValue::Rfc822Size(size) => fetch.size = Some(size),
+            AttributeValue::InternalDate(date) => fetch.internal_date = Some(date.to_string()),
+            AttributeValue::Rfc822(data) => fetch.body = data.map(|d| d.to_vec()),
+            AttributeValue::Rfc822Header(data) => fetch.header = data.map(|d| d.to_vec()),
+            AttributeValue::Rfc822Text(data) => fetch.text = data.map(|d| d.to_vec()),
+            AttributeValue::Envelope(e) => fetch.envelope = Some(parse_envelope(*e)),
+            AttributeValue::BodyStructure(b) => {
+                fetch.body_structure = Some(parse_body_structure(b))
+            }
+            AttributeValue::BodySection { section, data, .. } => {
+                if let Some(data) = data {
+                    fetch.sections.insert(section_key(&section), data.to_vec());
                 }
             }
-
-            MapOrNot::Map(fetch)
+            AttributeValue::ModSeq(modseq) => fetch.mod_seq = Some(modseq),
+            _ => {}
         }
-        resp => MapOrNot::Not(resp),
-    })
+    }
+
+    fetch
 }
 
-pub fn parse_capability<'a>(mut lines: &'a [u8]) -> Result<Vec<&'a str>> {
+/// Renders a `BODY[<section>]` section specifier back into the string the server used, e.g.
+/// `""`, `"TEXT"`, or `"HEADER.FIELDS (FROM TO)"`.
+fn section_key(section: &[imap_proto::types::Section]) -> String {
+    section
+        .iter()
+        .map(section_part_key)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Renders a single part of a `BODY[<section>]` specifier, e.g. a `Section::Part` as the
+/// dotted part number (`"1.2"`) or a `Section::HeaderFields` as `"HEADER.FIELDS (FROM TO)"`.
+fn section_part_key(part: &imap_proto::types::Section) -> String {
+    use imap_proto::types::Section;
+
+    match part {
+        Section::Part(nums) => nums
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join("."),
+        Section::Header => "HEADER".to_string(),
+        Section::HeaderFields(fields) => format!(
+            "HEADER.FIELDS ({})",
+            fields
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        ),
+        Section::HeaderFieldsNot(fields) => format!(
+            "HEADER.FIELDS.NOT ({})",
+            fields
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        ),
+        Section::Text => "TEXT".to_string(),
+        Section::Mime => "MIME".to_string(),
+    }
+}
+
+fn parse_envelope(e: imap_proto::types::Envelope) -> Envelope {
+    Envelope {
+        date: e.date.map(|d| d.to_vec()),
+        subject: e.subject.map(|d| d.to_vec()),
+        from: e.from.map(|v| v.into_iter().map(parse_address).collect()),
+        sender: e
+            .sender
+            .map(|v| v.into_iter().map(parse_address).collect()),
+        reply_to: e
+            .reply_to
+            .map(|v| v.into_iter().map(parse_address).collect()),
+        to: e.to.map(|v| v.into_iter().map(parse_address).collect()),
+        cc: e.cc.map(|v| v.into_iter().map(parse_address).collect()),
+        bcc: e.bcc.map(|v| v.into_iter().map(parse_address).collect()),
+        in_reply_to: e.in_reply_to.map(|d| d.to_vec()),
+        message_id: e.message_id.map(|d| d.to_vec()),
+    }
+}
+
+fn parse_address(a: imap_proto::types::Address) -> Address {
+    Address {
+        name: a.name.map(|d| d.to_vec()),
+        adl: a.adl.map(|d| d.to_vec()),
+        mailbox: a.mailbox.map(|d| d.to_vec()),
+        host: a.host.map(|d| d.to_vec()),
+    }
+}
+
+fn parse_body_structure(b: imap_proto::types::BodyStructure) -> BodyStructure {
+    match b {
+        imap_proto::types::BodyStructure::Multipart { subtype, bodies } => BodyStructure {
+            content_type: "MULTIPART".to_string(),
+            content_subtype: subtype.to_string(),
+            params: vec![],
+            id: None,
+            description: None,
+            encoding: String::new(),
+            size: 0,
+            parts: bodies.into_iter().map(parse_body_structure).collect(),
+        },
+        imap_proto::types::BodyStructure::Basic { common, other, .. }
+        | imap_proto::types::BodyStructure::Text { common, other, .. }
+        | imap_proto::types::BodyStructure::Message { common, other, .. } => BodyStructure {
+            content_type: common.ty.ty.to_string(),
+            content_subtype: common.ty.subtype.to_string(),
+            params: common
+                .ty
+                .params
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            id: common.id.map(|d| d.to_string()),
+            description: common.description.map(|d| d.to_string()),
+            encoding: common.transfer_encoding.to_string(),
+            size: other.octets,
+            parts: vec![],
+        },
+    }
+}
+
+pub fn parse_capabilities(
+    lines: &[u8],
+    unsolicited_responses_tx: &mut mpsc::Sender<UnsolicitedResponse>,
+) -> ZeroCopyResult<Capabilities> {
     let mut capabilities = Vec::new();
-    loop {
+    let caps = parse_many(
+        lines,
+        |resp| match resp {
+            Response::Capabilities(c) => MapOrNot::Map(c),
+            resp => MapOrNot::Not(resp),
+        },
+        unsolicited_responses_tx,
+    )?;
+    for c in caps {
+        capabilities.extend(c.into_iter().map(|s| s.to_string()));
+    }
+    Ok(Capabilities(capabilities))
+}
+
+/// Parses the `* ENABLED ...` response to an `ENABLE` command ([RFC
+/// 5161](https://tools.ietf.org/html/rfc5161#section-3.2)) into the same `Capabilities` type used
+/// for `CAPABILITY`, so callers can check what actually got enabled with `has`.
+pub fn parse_enabled(lines: &[u8]) -> ZeroCopyResult<Capabilities> {
+    let text = String::from_utf8(lines.to_vec()).map_err(|e| Error::Parse(ParseError::FromUtf8(e)))?;
+    let mut enabled = Vec::new();
+    for line in text.split("\r\n") {
+        let mut words = line.split(' ');
+        if words.next() == Some("*") && words.next() == Some("ENABLED") {
+            enabled.extend(words.map(|s| s.to_string()));
+        }
+    }
+    Ok(Capabilities(enabled))
+}
+
+/// Scans a completed `APPEND` response for the `[APPENDUID <uidvalidity> <uid>]` response code
+/// ([RFC 4315](https://tools.ietf.org/html/rfc4315#section-3)), returning `None` if the server
+/// didn't send one (e.g. it doesn't support UIDPLUS).
+pub fn parse_append_uid(lines: &[u8]) -> Result<Option<AppendUid>> {
+    use imap_proto::types::UidSetMember;
+    use imap_proto::ResponseCode;
+
+    let mut lines = lines;
+    let mut uid = None;
+    while !lines.is_empty() {
         match imap_proto::parse_response(lines) {
-            IResult::Done(rest, Response::Capabilities(c)) => {
+            Ok((rest, Response::Data(_, code, _))) => {
                 lines = rest;
-                capabilities.extend(c);
+                if let Some(ResponseCode::AppendUid(uid_validity, UidSetMember::Uid(new_uid))) =
+                    code
+                {
+                    uid = Some(AppendUid {
+                        uid_validity,
+                        uid: new_uid,
+                    });
+                }
+            }
+            Ok((rest, _)) => lines = rest,
+            Err(_) => break,
+        }
+    }
+    Ok(uid)
+}
 
-                if lines.is_empty() {
-                    break Ok(capabilities);
+fn metadata_invalid(lines: &[u8]) -> Error {
+    Error::Parse(ParseError::Invalid(lines.to_vec()))
+}
+
+fn skip_spaces(buf: &[u8], mut i: usize) -> usize {
+    while buf.get(i) == Some(&b' ') {
+        i += 1;
+    }
+    i
+}
+
+// Reads a quoted string, literal (`{n}\r\n<n bytes>`), or bare atom starting at `buf[i]`.
+fn parse_metadata_string(buf: &[u8], i: usize) -> Result<(Vec<u8>, usize)> {
+    match buf.get(i) {
+        Some(b'"') => {
+            let mut j = i + 1;
+            let mut out = Vec::new();
+            loop {
+                match buf.get(j) {
+                    Some(b'"') => break Ok((out, j + 1)),
+                    Some(b'\\') => {
+                        let escaped = *buf.get(j + 1).ok_or_else(|| metadata_invalid(buf))?;
+                        out.push(escaped);
+                        j += 2;
+                    }
+                    Some(&b) => {
+                        out.push(b);
+                        j += 1;
+                    }
+                    None => break Err(metadata_invalid(buf)),
                 }
             }
-            IResult::Done(_, resp) => {
-                break Err(resp.into());
+        }
+        Some(b'{') => {
+            let digits_start = i + 1;
+            let mut j = digits_start;
+            while buf.get(j).map_or(false, u8::is_ascii_digit) {
+                j += 1;
+            }
+            let n: usize = std::str::from_utf8(&buf[digits_start..j])
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(|| metadata_invalid(buf))?;
+            if buf.get(j) == Some(&b'+') {
+                j += 1;
+            }
+            if buf.get(j) != Some(&b'}') || buf.get(j + 1..j + 3) != Some(b"\r\n") {
+                return Err(metadata_invalid(buf));
+            }
+            j += 3;
+            let value = buf
+                .get(j..j + n)
+                .ok_or_else(|| metadata_invalid(buf))?
+                .to_vec();
+            Ok((value, j + n))
+        }
+        Some(_) => {
+            let start = i;
+            let mut j = i;
+            while buf
+                .get(j)
+                .map_or(false, |&b| !b" ()\r\n".contains(&b))
+            {
+                j += 1;
+            }
+            if j == start {
+                return Err(metadata_invalid(buf));
+            }
+            Ok((buf[start..j].to_vec(), j))
+        }
+        None => Err(metadata_invalid(buf)),
+    }
+}
+
+// A metadata value is a string/literal, or the atom `NIL` meaning the entry is unset.
+fn parse_metadata_value(buf: &[u8], i: usize) -> Result<(Option<Vec<u8>>, usize)> {
+    if buf[i..].starts_with(b"NIL") {
+        return Ok((None, i + 3));
+    }
+    let (value, next) = parse_metadata_string(buf, i)?;
+    Ok((Some(value), next))
+}
+
+/// Parses `* METADATA <mailbox> (<entry> <value> ...)` responses to `GETMETADATA` ([RFC
+/// 5464](https://tools.ietf.org/html/rfc5464#section-4.4)) into `(entry, value)` pairs. A value
+/// is `None` when the server reported the entry as `NIL`. Handles both quoted-string and literal
+/// (`{n}\r\n...`) value syntax, since servers commonly return large annotation values as literals.
+pub fn parse_metadata(
+    lines: &[u8],
+    unsolicited_responses_tx: &mut mpsc::Sender<UnsolicitedResponse>,
+) -> Result<Vec<(String, Option<Vec<u8>>)>> {
+    let mut metadata = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i..].starts_with(b"* METADATA") {
+            i += b"* METADATA".len();
+            i = skip_spaces(lines, i);
+            let (_mailbox, next) = parse_metadata_string(lines, i)?;
+            i = skip_spaces(lines, next);
+            if lines.get(i) != Some(&b'(') {
+                return Err(metadata_invalid(lines));
+            }
+            i += 1;
+            loop {
+                i = skip_spaces(lines, i);
+                if lines.get(i) == Some(&b')') {
+                    i += 1;
+                    break;
+                }
+                let (entry, next) = parse_metadata_string(lines, i)?;
+                let entry = String::from_utf8(entry)?;
+                i = skip_spaces(lines, next);
+                let (value, next) = parse_metadata_value(lines, i)?;
+                metadata.push((entry, value));
+                i = next;
+            }
+        } else if let Ok((_, resp)) = imap_proto::parse_response(&lines[i..]) {
+            if let Ok(r) = to_unsolicited(resp) {
+                unsolicited_responses_tx.send(r).unwrap();
+            }
+        }
+
+        match lines[i..].iter().position(|&b| b == b'\n') {
+            Some(pos) => i += pos + 1,
+            None => break,
+        }
+    }
+    Ok(metadata)
+}
+
+pub fn parse_ids(
+    lines: &[u8],
+    unsolicited_responses_tx: &mut mpsc::Sender<UnsolicitedResponse>,
+) -> Result<HashSet<u32>> {
+    let mut ids = HashSet::new();
+    let id_lists = parse_many(
+        lines,
+        |resp| match resp {
+            Response::MailboxData(MailboxDatum::Search(v)) => MapOrNot::Map(v),
+            resp => MapOrNot::Not(resp),
+        },
+        unsolicited_responses_tx,
+    )?;
+    for list in id_lists {
+        ids.extend(list);
+    }
+    Ok(ids)
+}
+
+/// Expands a sequence-set such as `3:10,15` (as seen in `* ESEARCH ... ALL 3:10,15`) into the
+/// individual ids it names.
+fn parse_sequence_set(set: &str) -> Result<HashSet<u32>> {
+    let invalid = || Error::Parse(ParseError::Invalid(set.as_bytes().to_vec()));
+    let mut ids = HashSet::new();
+    for part in set.split(',') {
+        let mut bounds = part.splitn(2, ':');
+        let start: u32 = bounds.next().ok_or_else(invalid)?.parse().map_err(|_| invalid())?;
+        match bounds.next() {
+            Some(end) => {
+                let end: u32 = end.parse().map_err(|_| invalid())?;
+                ids.extend(start..=end);
+            }
+            None => {
+                ids.insert(start);
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// Parses the response to a `SEARCH RETURN (...)`/`UID SEARCH RETURN (...)` command ([RFC
+/// 4731](https://tools.ietf.org/html/rfc4731)): the `* ESEARCH ...` untagged response, or the
+/// legacy `* SEARCH ...` response a server that doesn't support ESEARCH falls back to.
+pub fn parse_search_return(
+    lines: &[u8],
+    unsolicited_responses_tx: &mut mpsc::Sender<UnsolicitedResponse>,
+) -> ZeroCopyResult<SearchResult> {
+    let text = String::from_utf8(lines.to_vec()).map_err(|e| Error::Parse(ParseError::FromUtf8(e)))?;
+    let mut result = SearchResult::default();
+    for line in text.split("\r\n") {
+        let mut words = line.split(' ').peekable();
+        match (words.next(), words.next()) {
+            (Some("*"), Some("ESEARCH")) => {
+                // Skip the `(TAG "...")` search-correlator, if the server sent one.
+                if words.peek() == Some(&"(TAG") {
+                    words.next();
+                    words.next();
+                }
+                while let Some(word) = words.next() {
+                    match word {
+                        "UID" => result.uid = true,
+                        "MIN" => result.min = words.next().and_then(|n| n.parse().ok()),
+                        "MAX" => result.max = words.next().and_then(|n| n.parse().ok()),
+                        "COUNT" => result.count = words.next().and_then(|n| n.parse().ok()),
+                        "ALL" => {
+                            if let Some(set) = words.next() {
+                                result.all = parse_sequence_set(set)?;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            // A server without ESEARCH support replies to `SEARCH RETURN` the same way it would
+            // a plain `SEARCH`; treat every id it returns as `ALL`.
+            (Some("*"), Some("SEARCH")) => {
+                result.all.extend(words.filter_map(|n| n.parse().ok()));
             }
             _ => {
-                break Err(Error::Parse(ParseError::Invalid(lines.to_vec())));
+                if let Ok((_, resp)) = imap_proto::parse_response(line.as_bytes()) {
+                    if let Ok(r) = to_unsolicited(resp) {
+                        unsolicited_responses_tx.send(r).unwrap();
+                    }
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Scans a completed `COPY`/`MOVE`/`UID MOVE` response for the `[COPYUID <uidvalidity>
+/// <source-uid-set> <dest-uid-set>]` response code ([RFC 4315](https://tools.ietf.org/html/rfc4315#section-3)),
+/// zipping the two (already-expanded) uid-sets together positionally. Returns `None` if the
+/// server didn't send one (e.g. it doesn't support UIDPLUS). Any `EXPUNGE` lines interleaved with
+/// it (as `MOVE` sends) are forwarded to `unsolicited_responses_tx` like any other untagged
+/// response.
+pub fn parse_copy_uid(
+    mut lines: &[u8],
+    unsolicited_responses_tx: &mut mpsc::Sender<UnsolicitedResponse>,
+) -> Result<Option<CopyUid>> {
+    use imap_proto::ResponseCode;
+
+    let mut copy_uid = None;
+    while !lines.is_empty() {
+        match imap_proto::parse_response(lines) {
+            Ok((rest, resp)) => {
+                lines = rest;
+                match resp {
+                    Response::Data(_, Some(ResponseCode::CopyUid(uid_validity, source, dest)), _) => {
+                        copy_uid = Some(CopyUid {
+                            uid_validity,
+                            uids: source.into_iter().zip(dest.into_iter()).collect(),
+                        });
+                    }
+                    resp => match to_unsolicited(resp) {
+                        Ok(r) => {
+                            unsolicited_responses_tx.send(r).unwrap();
+                        }
+                        Err(resp) => return Err(resp.into()),
+                    },
+                }
             }
+            _ => return Err(Error::Parse(ParseError::Invalid(lines.to_vec()))),
         }
     }
+    Ok(copy_uid)
 }
 
-pub fn parse_mailbox(mut lines: &[u8]) -> Result<Mailbox> {
+pub fn parse_mailbox(
+    mut lines: &[u8],
+    unsolicited_responses_tx: &mut mpsc::Sender<UnsolicitedResponse>,
+) -> Result<Mailbox> {
     let mut mailbox = Mailbox::default();
 
     loop {
         match imap_proto::parse_response(lines) {
-            IResult::Done(rest, Response::Data(status, rcode, _)) => {
+            Ok((rest, Response::Data(status, rcode, _))) => {
                 lines = rest;
 
                 if let imap_proto::Status::Ok = status {
@@ -130,20 +594,32 @@ pub fn parse_mailbox(mut lines: &[u8]) -> Result<Mailbox> {
                     Some(ResponseCode::UidNext(unext)) => {
                         mailbox.uid_next = Some(unext);
                     }
+                    Some(ResponseCode::Unseen(n)) => {
+                        mailbox.unseen = Some(n);
+                    }
                     Some(ResponseCode::PermanentFlags(flags)) => {
                         mailbox
                             .permanent_flags
                             .extend(flags.into_iter().map(|s| s.to_string()));
                     }
-                    // TODO: UNSEEN
-                    // https://github.com/djc/imap-proto/issues/2
+                    Some(ResponseCode::HighestModSeq(modseq)) => {
+                        mailbox.highest_mod_seq = Some(modseq);
+                    }
+                    // NOMODSEQ means the mailbox doesn't support persistent mod-sequences; leave
+                    // `highest_mod_seq` as `None`, same as if the server hadn't mentioned it.
+                    Some(ResponseCode::NoModSeq) => {}
+                    Some(ResponseCode::ReadOnly) => {
+                        mailbox.read_only = true;
+                    }
+                    Some(ResponseCode::ReadWrite) => {
+                        mailbox.read_only = false;
+                    }
                     _ => {}
                 }
             }
-            IResult::Done(rest, Response::MailboxData(m)) => {
+            Ok((rest, Response::MailboxData(m))) => {
                 lines = rest;
 
-                use imap_proto::MailboxDatum;
                 match m {
                     MailboxDatum::Exists(e) => {
                         mailbox.exists = e;
@@ -157,9 +633,25 @@ pub fn parse_mailbox(mut lines: &[u8]) -> Result<Mailbox> {
                             .extend(flags.into_iter().map(|s| s.to_string()));
                     }
                     MailboxDatum::List(..) => {}
+                    _ => {}
                 }
             }
-            IResult::Done(_, resp) => {
+            // A `QRESYNC` `SELECT` interleaves `VANISHED (EARLIER)` and changed `FETCH`
+            // responses with the usual mailbox data; neither is part of the `Mailbox` itself, so
+            // hand them to the unsolicited channel instead.
+            Ok((rest, Response::Fetch(num, attrs))) => {
+                lines = rest;
+                unsolicited_responses_tx
+                    .send(UnsolicitedResponse::Fetch(fetch_from_attrs(num, attrs)))
+                    .unwrap();
+            }
+            Ok((rest, Response::Vanished { earlier: _, uids })) => {
+                lines = rest;
+                unsolicited_responses_tx
+                    .send(UnsolicitedResponse::Vanished(uids.iter().cloned().collect()))
+                    .unwrap();
+            }
+            Ok((_, resp)) => {
                 break Err(resp.into());
             }
             _ => {
@@ -176,6 +668,11 @@ pub fn parse_mailbox(mut lines: &[u8]) -> Result<Mailbox> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::mpsc;
+
+    fn tx() -> mpsc::Sender<UnsolicitedResponse> {
+        mpsc::channel().0
+    }
 
     #[test]
     fn parse_capability_test() {
@@ -186,9 +683,9 @@ mod tests {
             String::from("LOGINDISABLED"),
         ];
         let lines = b"* CAPABILITY IMAP4rev1 STARTTLS AUTH=GSSAPI LOGINDISABLED\r\n";
-        let capabilities = parse_capability(lines).unwrap();
+        let capabilities = parse_capabilities(lines, &mut tx()).unwrap();
         assert!(
-            capabilities == expected_capabilities,
+            expected_capabilities.iter().all(|c| capabilities.has(c)),
             "Unexpected capabilities parse response"
         );
     }
@@ -197,23 +694,64 @@ mod tests {
     #[should_panic]
     fn parse_capability_invalid_test() {
         let lines = b"* JUNK IMAP4rev1 STARTTLS AUTH=GSSAPI LOGINDISABLED\r\n";
-        parse_capability(lines).unwrap();
+        parse_capabilities(lines, &mut tx()).unwrap();
+    }
+
+    #[test]
+    fn parse_enabled_test() {
+        let lines = b"* ENABLED CONDSTORE QRESYNC\r\n";
+        let enabled = parse_enabled(lines).unwrap();
+        assert!(enabled.has("CONDSTORE"));
+        assert!(enabled.has("QRESYNC"));
+        assert_eq!(enabled.len(), 2);
+    }
+
+    #[test]
+    fn parse_metadata_test() {
+        let lines = b"* METADATA \"INBOX\" (/private/comment \"My comment\" /private/other NIL)\r\n";
+        let metadata = parse_metadata(lines, &mut tx()).unwrap();
+        assert_eq!(
+            metadata,
+            vec![
+                ("/private/comment".to_string(), Some(b"My comment".to_vec())),
+                ("/private/other".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_metadata_literal_test() {
+        // The literal's 5 bytes include a raw CRLF, which must be read as part of the value
+        // rather than treated as ending the response line.
+        let lines = b"* METADATA \"\" (/shared/vendor/foo {5}\r\nhel\r\n)\r\n";
+        let metadata = parse_metadata(lines, &mut tx()).unwrap();
+        assert_eq!(
+            metadata,
+            vec![("/shared/vendor/foo".to_string(), Some(b"hel\r\n".to_vec()))]
+        );
     }
 
     #[test]
     fn parse_names_test() {
         let lines = b"* LIST (\\HasNoChildren) \".\" \"INBOX\"\r\n";
-        let names = parse_names(lines).unwrap();
+        let names = parse_names(lines, &mut tx()).unwrap();
         assert_eq!(
-            vec![
-                Name {
-                    attributes: vec!["\\HasNoChildren".to_string()],
-                    delimiter: ".".to_string(),
-                    name: "INBOX".to_string(),
-                },
-            ],
+            vec![Name {
+                attributes: vec!["\\HasNoChildren".to_string()],
+                delimiter: ".".to_string(),
+                raw_name: "INBOX".to_string(),
+            }],
             names
         );
+        assert_eq!(names[0].name(), "INBOX");
+    }
+
+    #[test]
+    fn parse_names_decodes_modified_utf7() {
+        let lines = b"* LIST (\\HasNoChildren) \".\" \"&ZeVnLIqe-\"\r\n";
+        let names = parse_names(lines, &mut tx()).unwrap();
+        assert_eq!(names[0].raw_name, "&ZeVnLIqe-");
+        assert_eq!(names[0].name(), "\u{65e5}\u{672c}\u{8a9e}");
     }
 
     #[test]
@@ -221,18 +759,19 @@ mod tests {
         let lines = b"\
                     * 24 FETCH (FLAGS (\\Seen) UID 4827943)\r\n\
                     * 25 FETCH (FLAGS (\\Seen))\r\n";
-        let fetches = parse_fetches(lines).unwrap();
+        let fetches = parse_fetches(lines, &mut tx()).unwrap();
         assert_eq!(
             vec![
                 Fetch {
                     message: 24,
                     flags: vec!["\\Seen".to_string()],
                     uid: Some(4827943),
+                    ..Fetch::default()
                 },
                 Fetch {
                     message: 25,
                     flags: vec!["\\Seen".to_string()],
-                    uid: None,
+                    ..Fetch::default()
                 },
             ],
             fetches
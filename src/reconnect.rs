@@ -0,0 +1,171 @@
+//! An offline-tolerant wrapper around [`Session`] for long-running clients.
+//!
+//! A daemon or sync agent that keeps a `Session` open for a long time will eventually see its
+//! underlying connection die from an idle timeout or a network blip; today that surfaces as a
+//! transient error (see [`Error::is_transient`](../error/enum.Error.html#method.is_transient))
+//! that leaves the `Session` unusable. [`ReconnectSession`] catches exactly those errors, re-dials
+//! and re-authenticates using a caller-supplied [`Connect`] implementation, re-`SELECT`s the
+//! mailbox that was selected before the connection dropped, and retries the failed command.
+
+use std::collections::HashSet;
+use std::io::{Read, Write};
+
+use super::client::{Session, SetReadTimeout};
+use super::error::{Error, Result};
+use super::types::*;
+
+/// Produces a fresh, already-authenticated [`Session`] for a [`ReconnectSession`] to fall back on
+/// after its connection is lost. Implementations typically close over the server address, the
+/// `TlsConnector` used to secure it, and login credentials, and re-run the same `connect`/`login`
+/// sequence used to create the original session.
+///
+/// Any `FnMut() -> Result<Session<T>>` implements this automatically.
+pub trait Connect<T: Read + Write + SetReadTimeout> {
+    /// Dials and authenticates a new session.
+    fn connect(&mut self) -> Result<Session<T>>;
+}
+
+impl<T, F> Connect<T> for F
+where
+    T: Read + Write + SetReadTimeout,
+    F: FnMut() -> Result<Session<T>>,
+{
+    fn connect(&mut self) -> Result<Session<T>> {
+        self()
+    }
+}
+
+/// A wrapper around [`Session`] that transparently reconnects when the connection is lost.
+///
+/// It stores the name of the last mailbox selected through it, so that on reconnect it can
+/// restore that state before the triggering command is retried. Since a fresh connection may see
+/// a different `UIDVALIDITY`, register an [`on_reconnect`](#method.on_reconnect) hook to know when
+/// cached UIDs need revalidating.
+pub struct ReconnectSession<T: Read + Write + SetReadTimeout, C: Connect<T>> {
+    session: Session<T>,
+    connect: C,
+    selected_mailbox: Option<String>,
+    max_retries: u32,
+    on_reconnect: Option<Box<dyn FnMut()>>,
+}
+
+impl<T: Read + Write + SetReadTimeout, C: Connect<T>> ReconnectSession<T, C> {
+    /// Wraps an already-authenticated `session`. `connect` is used to re-establish the connection
+    /// (dialing, TLS, and login) whenever it's lost; it's usually a closure capturing the same
+    /// parameters that produced `session` in the first place.
+    pub fn new(session: Session<T>, connect: C) -> Self {
+        ReconnectSession {
+            session,
+            connect,
+            selected_mailbox: None,
+            max_retries: 1,
+            on_reconnect: None,
+        }
+    }
+
+    /// Sets how many times a failed command may trigger a reconnect-and-retry before its error is
+    /// given up and returned. Defaults to 1.
+    pub fn set_max_retries(&mut self, max_retries: u32) {
+        self.max_retries = max_retries;
+    }
+
+    /// Registers a hook that's called every time the connection is successfully reestablished
+    /// after being lost, before the triggering command is retried. Since the new connection may
+    /// have a different `UIDVALIDITY`, this is the place to invalidate any UID-keyed caches.
+    pub fn on_reconnect<F: FnMut() + 'static>(&mut self, hook: F) {
+        self.on_reconnect = Some(Box::new(hook));
+    }
+
+    /// The underlying session, for calls this wrapper doesn't expose directly. Note that any
+    /// `Error::Io`/`Error::ConnectionLost` from using it directly will *not* trigger a reconnect.
+    pub fn session(&mut self) -> &mut Session<T> {
+        &mut self.session
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        let mut session = self.connect.connect()?;
+        if let Some(ref mailbox) = self.selected_mailbox {
+            session.select(mailbox)?;
+        }
+        self.session = session;
+        if let Some(ref mut hook) = self.on_reconnect {
+            hook();
+        }
+        Ok(())
+    }
+
+    fn retry<F, R>(&mut self, mut op: F) -> Result<R>
+    where
+        F: FnMut(&mut Session<T>) -> Result<R>,
+    {
+        let mut attempts = 0;
+        loop {
+            match op(&mut self.session) {
+                Err(ref e) if e.is_transient() && attempts < self.max_retries => {
+                    attempts += 1;
+                    self.reconnect()?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// See [`Session::select`](../client/struct.Session.html#method.select).
+    pub fn select(&mut self, mailbox_name: &str) -> Result<Mailbox> {
+        let mailbox = self.retry(|s| s.select(mailbox_name))?;
+        self.selected_mailbox = Some(mailbox_name.to_string());
+        Ok(mailbox)
+    }
+
+    /// See [`Session::examine`](../client/struct.Session.html#method.examine).
+    pub fn examine(&mut self, mailbox_name: &str) -> Result<Mailbox> {
+        let mailbox = self.retry(|s| s.examine(mailbox_name))?;
+        self.selected_mailbox = Some(mailbox_name.to_string());
+        Ok(mailbox)
+    }
+
+    /// See [`Session::fetch`](../client/struct.Session.html#method.fetch).
+    pub fn fetch(&mut self, sequence_set: &str, query: &str) -> ZeroCopyResult<Vec<Fetch>> {
+        self.retry(|s| s.fetch(sequence_set, query))
+    }
+
+    /// See [`Session::uid_fetch`](../client/struct.Session.html#method.uid_fetch).
+    pub fn uid_fetch(&mut self, uid_set: &str, query: &str) -> ZeroCopyResult<Vec<Fetch>> {
+        self.retry(|s| s.uid_fetch(uid_set, query))
+    }
+
+    /// See [`Session::store`](../client/struct.Session.html#method.store).
+    pub fn store(&mut self, sequence_set: &str, query: &str) -> ZeroCopyResult<Vec<Fetch>> {
+        self.retry(|s| s.store(sequence_set, query))
+    }
+
+    /// See [`Session::uid_store`](../client/struct.Session.html#method.uid_store).
+    pub fn uid_store(&mut self, uid_set: &str, query: &str) -> ZeroCopyResult<Vec<Fetch>> {
+        self.retry(|s| s.uid_store(uid_set, query))
+    }
+
+    /// See [`Session::search`](../client/struct.Session.html#method.search).
+    pub fn search(&mut self, query: &str) -> Result<HashSet<u32>> {
+        self.retry(|s| s.search(query))
+    }
+
+    /// See [`Session::uid_search`](../client/struct.Session.html#method.uid_search).
+    pub fn uid_search(&mut self, query: &str) -> Result<HashSet<u32>> {
+        self.retry(|s| s.uid_search(query))
+    }
+
+    /// See [`Session::noop`](../client/struct.Session.html#method.noop).
+    pub fn noop(&mut self) -> Result<()> {
+        self.retry(|s| s.noop())
+    }
+
+    /// See [`Session::expunge`](../client/struct.Session.html#method.expunge).
+    pub fn expunge(&mut self) -> Result<()> {
+        self.retry(|s| s.expunge())
+    }
+
+    /// See [`Session::uid_expunge`](../client/struct.Session.html#method.uid_expunge).
+    pub fn uid_expunge(&mut self, uid_set: &str) -> Result<()> {
+        self.retry(|s| s.uid_expunge(uid_set))
+    }
+}
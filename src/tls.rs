@@ -0,0 +1,90 @@
+//! Pluggable TLS backends for upgrading a plain connection to an encrypted one.
+//!
+//! [`connect_starttls`](../client/fn.connect_starttls.html),
+//! [`secure_connect`](../client/fn.secure_connect.html), and [`Client::secure`](../client/struct.Client.html#method.secure) are generic over any type
+//! implementing [`TlsConnector`], so callers choose a TLS backend via Cargo feature flags instead
+//! of this crate hard-coding one onto OpenSSL. Enable the `native-tls` feature (on by default) for
+//! a [`native_tls`] backend, or `rustls-tls` for a pure-Rust [`rustls`] backend; both may be
+//! enabled at once, and callers pick one by passing its connector value.
+
+use std::io::{Read, Write};
+
+use super::error::Result;
+
+/// A TLS backend able to upgrade an established, unencrypted `Read + Write` stream to an
+/// encrypted one, verifying the peer's certificate against `domain`.
+pub trait TlsConnector<T: Read + Write> {
+    /// The encrypted stream type this backend produces.
+    type Stream: Read + Write;
+
+    /// Performs the TLS handshake over `stream`, verifying the server's certificate for `domain`.
+    fn connect(&self, domain: &str, stream: T) -> Result<Self::Stream>;
+}
+
+#[cfg(feature = "native-tls")]
+mod native_tls_support {
+    use std::net::TcpStream;
+    use std::time::Duration;
+
+    use native_tls::TlsStream;
+
+    use super::super::client::SetReadTimeout;
+    use super::super::error::Error;
+    use super::TlsConnector;
+
+    impl TlsConnector<TcpStream> for native_tls::TlsConnector {
+        type Stream = TlsStream<TcpStream>;
+
+        fn connect(&self, domain: &str, stream: TcpStream) -> super::Result<Self::Stream> {
+            native_tls::TlsConnector::connect(self, domain, stream).map_err(Error::TlsHandshake)
+        }
+    }
+
+    impl SetReadTimeout for TlsStream<TcpStream> {
+        fn set_read_timeout(&mut self, timeout: Option<Duration>) -> super::Result<()> {
+            self.get_ref().set_read_timeout(timeout).map_err(Error::Io)
+        }
+    }
+}
+
+#[cfg(feature = "rustls-tls")]
+mod rustls_support {
+    use std::convert::TryFrom;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use rustls::{ClientConfig, ClientConnection, ServerName, StreamOwned};
+
+    use super::super::client::SetReadTimeout;
+    use super::super::error::Error;
+    use super::TlsConnector;
+
+    /// Wraps a `rustls::ClientConfig` so it can be used as a [`TlsConnector`](super::TlsConnector).
+    #[derive(Clone)]
+    pub struct RustlsConnector(pub Arc<ClientConfig>);
+
+    impl<T: Read + Write> TlsConnector<T> for RustlsConnector {
+        type Stream = StreamOwned<ClientConnection, T>;
+
+        fn connect(&self, domain: &str, stream: T) -> super::Result<Self::Stream> {
+            let server_name = ServerName::try_from(domain)
+                .map_err(|_| Error::Rustls(format!("invalid domain name: {}", domain)))?;
+            let conn = ClientConnection::new(self.0.clone(), server_name)
+                .map_err(|e| Error::Rustls(e.to_string()))?;
+            Ok(StreamOwned::new(conn, stream))
+        }
+    }
+
+    impl SetReadTimeout for StreamOwned<ClientConnection, TcpStream> {
+        fn set_read_timeout(&mut self, timeout: Option<Duration>) -> super::Result<()> {
+            self.get_ref()
+                .set_read_timeout(timeout)
+                .map_err(Error::Io)
+        }
+    }
+}
+
+#[cfg(feature = "rustls-tls")]
+pub use self::rustls_support::RustlsConnector;
@@ -1,6 +1,227 @@
-#[derive(Debug, Eq, PartialEq)]
+use std::collections::HashMap;
+
+/// An electronic mail address, as found in an `ENVELOPE` fetch response. Refer to [RFC 3501,
+/// section 7.4.2](https://tools.ietf.org/html/rfc3501#section-7.4.2).
+#[derive(Debug, Eq, PartialEq, Default, Clone)]
+pub struct Address {
+    /// The personal/display name of the mailbox, if any was given.
+    pub name: Option<Vec<u8>>,
+    /// The SMTP at-domain-list (source route), rarely used today.
+    pub adl: Option<Vec<u8>>,
+    /// The mailbox name, i.e. the local-part of the address.
+    pub mailbox: Option<Vec<u8>>,
+    /// The host name, i.e. the domain-part of the address.
+    pub host: Option<Vec<u8>>,
+}
+
+impl Address {
+    /// Renders this address as `mailbox@host`, if both parts are present.
+    ///
+    /// Per [RFC 3501, section 7.4.2](https://tools.ietf.org/html/rfc3501#section-7.4.2), an
+    /// `ENVELOPE` address list may contain RFC 822 group markers: an address with a `mailbox`
+    /// but a `NIL` `host` starts a group (`mailbox` is the group name), and a fully-`NIL`
+    /// address ends it. Neither is a real mailbox, so this returns `None` for both.
+    pub fn address(&self) -> Option<String> {
+        match (&self.mailbox, &self.host) {
+            (Some(mailbox), Some(host)) => Some(format!(
+                "{}@{}",
+                String::from_utf8_lossy(mailbox),
+                String::from_utf8_lossy(host)
+            )),
+            _ => None,
+        }
+    }
+
+    /// Whether this address is an RFC 822 group start/end marker rather than a real mailbox (see
+    /// [`address`](#method.address)).
+    pub fn is_group_marker(&self) -> bool {
+        self.host.is_none()
+    }
+
+    /// The display name with any RFC 2047 encoded-words decoded to UTF-8.
+    pub fn decoded_name(&self) -> Option<String> {
+        self.name
+            .as_ref()
+            .map(|n| decode_encoded_words(&String::from_utf8_lossy(n)))
+    }
+}
+
+/// Decodes RFC 2047 `=?charset?encoding?text?=` encoded-words that may appear in an address's
+/// display name. The charset is not consulted: decoded bytes are always read as UTF-8, with
+/// invalid sequences replaced by U+FFFD. For an encoded-word with an encoding other than `B`/`Q`,
+/// or whose `B` text is not valid base64, only its text part is kept, without the `=?...?=`
+/// wrapper.
+fn decode_encoded_words(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("=?") {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start + 2..];
+        let parts: Vec<&str> = tail.splitn(4, '?').collect();
+        if parts.len() != 4 {
+            out.push_str(&rest[start..]);
+            return out;
+        }
+        let (_charset, encoding, text) = (parts[0], parts[1], parts[2]);
+        let end_marker = format!("{}?=", text);
+        let consumed = start + 2 + parts[0].len() + 1 + encoding.len() + 1 + end_marker.len();
+        match encoding.to_ascii_uppercase().as_str() {
+            "B" => match base64::decode(text) {
+                Ok(bytes) => out.push_str(&String::from_utf8_lossy(&bytes)),
+                Err(_) => out.push_str(text),
+            },
+            "Q" => out.push_str(&quoted_printable_decode(text)),
+            _ => out.push_str(text),
+        }
+        rest = &rest[consumed.min(rest.len())..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Decodes RFC 2047 quoted-printable ("Q") encoding: `_` is a space, and `=XX` is a hex-encoded
+/// byte.
+fn quoted_printable_decode(text: &str) -> String {
+    let mut out = Vec::with_capacity(text.len());
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'_' => {
+                out.push(b' ');
+                i += 1;
+            }
+            b'=' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 3;
+                    }
+                    None => {
+                        out.push(bytes[i]);
+                        i += 1;
+                    }
+                }
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// The structured `ENVELOPE` fetch item, describing a message's header fields. Refer to [RFC
+/// 3501, section 7.4.2](https://tools.ietf.org/html/rfc3501#section-7.4.2).
+#[derive(Debug, Eq, PartialEq, Default, Clone)]
+pub struct Envelope {
+    /// The message's `Date` header field.
+    pub date: Option<Vec<u8>>,
+    /// The message's `Subject` header field.
+    pub subject: Option<Vec<u8>>,
+    /// The message's `From` header field.
+    pub from: Option<Vec<Address>>,
+    /// The message's `Sender` header field.
+    pub sender: Option<Vec<Address>>,
+    /// The message's `Reply-To` header field.
+    pub reply_to: Option<Vec<Address>>,
+    /// The message's `To` header field.
+    pub to: Option<Vec<Address>>,
+    /// The message's `Cc` header field.
+    pub cc: Option<Vec<Address>>,
+    /// The message's `Bcc` header field.
+    pub bcc: Option<Vec<Address>>,
+    /// The message's `In-Reply-To` header field.
+    pub in_reply_to: Option<Vec<u8>>,
+    /// The message's `Message-ID` header field.
+    pub message_id: Option<Vec<u8>>,
+}
+
+/// A single part of a (possibly multipart) `BODYSTRUCTURE` fetch item. Refer to [RFC 3501,
+/// section 7.4.2](https://tools.ietf.org/html/rfc3501#section-7.4.2).
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct BodyStructure {
+    /// The MIME type, e.g. `"TEXT"` or `"MULTIPART"`.
+    pub content_type: String,
+    /// The MIME subtype, e.g. `"PLAIN"` or `"MIXED"`.
+    pub content_subtype: String,
+    /// Body parameters, such as `("CHARSET", "UTF-8")`.
+    pub params: Vec<(String, String)>,
+    /// The content id of this part, if any.
+    pub id: Option<String>,
+    /// The content description of this part, if any.
+    pub description: Option<String>,
+    /// The content transfer encoding, e.g. `"7BIT"` or `"BASE64"`.
+    pub encoding: String,
+    /// The size of this part in octets.
+    pub size: u32,
+    /// For `MULTIPART` bodies, the sub-parts that make it up. Empty for single-part bodies.
+    pub parts: Vec<BodyStructure>,
+}
+
+/// The result of a `FETCH` or `UID FETCH` command: a single message's fetched attributes. Refer
+/// to [RFC 3501, section 7.4.2](https://tools.ietf.org/html/rfc3501#section-7.4.2).
+#[derive(Debug, Eq, PartialEq, Default, Clone)]
 pub struct Fetch {
+    /// The message sequence number of the message.
     pub message: u32,
+    /// A list of flags that are set for this message.
     pub flags: Vec<String>,
+    /// The unique identifier of the message, if `UID` was one of the requested items.
     pub uid: Option<u32>,
+    /// The internal date of the message, as a raw IMAP date-time string, if `INTERNALDATE` was
+    /// requested.
+    pub internal_date: Option<String>,
+    /// The size of the message in octets (`RFC822.SIZE`), if requested.
+    pub size: Option<u32>,
+    /// The full `RFC822`/`BODY[]` payload, if requested.
+    pub body: Option<Vec<u8>>,
+    /// The `RFC822.HEADER`/`BODY[HEADER]` payload, if requested.
+    pub header: Option<Vec<u8>>,
+    /// The `RFC822.TEXT`/`BODY[TEXT]` payload, if requested.
+    pub text: Option<Vec<u8>>,
+    /// The raw bytes of every `BODY[<section>]`/`BODY.PEEK[<section>]` response, keyed by the
+    /// section specifier exactly as the server sent it (e.g. `""`, `"TEXT"`,
+    /// `"HEADER.FIELDS (FROM TO)"`).
+    pub sections: HashMap<String, Vec<u8>>,
+    /// The structured `ENVELOPE`, if requested.
+    pub envelope: Option<Envelope>,
+    /// The parsed `BODYSTRUCTURE`/`BODY`, if requested.
+    pub body_structure: Option<BodyStructure>,
+    /// The per-message modification sequence (`MODSEQ`), present when the server supports
+    /// `CONDSTORE` (RFC 7162) and the client asked for it.
+    pub mod_seq: Option<u64>,
+}
+
+impl Fetch {
+    /// The full message body (`BODY[]`/`RFC822`), if it was requested.
+    pub fn body(&self) -> Option<&[u8]> {
+        match self.body {
+            Some(ref b) => Some(b.as_slice()),
+            None => self.section(""),
+        }
+    }
+
+    /// The message header (`BODY[HEADER]`/`RFC822.HEADER`), if it was requested.
+    pub fn header(&self) -> Option<&[u8]> {
+        match self.header {
+            Some(ref b) => Some(b.as_slice()),
+            None => self.section("HEADER"),
+        }
+    }
+
+    /// The message text (`BODY[TEXT]`/`RFC822.TEXT`), if it was requested.
+    pub fn text(&self) -> Option<&[u8]> {
+        match self.text {
+            Some(ref b) => Some(b.as_slice()),
+            None => self.section("TEXT"),
+        }
+    }
+
+    /// The raw bytes of the `BODY[<section>]` response matching `section` exactly, if any.
+    pub fn section(&self, section: &str) -> Option<&[u8]> {
+        self.sections.get(section).map(|b| b.as_slice())
+    }
 }
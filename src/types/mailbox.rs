@@ -0,0 +1,44 @@
+/// Responses to the `SELECT`, `EXAMINE`, and `STATUS` commands, describing the state of the
+/// (possibly newly-selected) mailbox.
+#[derive(Debug, Eq, PartialEq, Default, Clone)]
+pub struct Mailbox {
+    /// Defined flags in the mailbox. Flags other than the system flags (\Seen, \Answered,
+    /// \Flagged, \Deleted, \Draft, \Recent) can be found in the mailbox too.
+    pub flags: Vec<String>,
+
+    /// The number of messages in this mailbox.
+    pub exists: u32,
+
+    /// The number of messages with the \Recent flag set.
+    pub recent: u32,
+
+    /// The message sequence number of the first unseen message in the mailbox, if any was
+    /// reported by the server.
+    pub unseen: Option<u32>,
+
+    /// A list of message flags that the client can change permanently.
+    pub permanent_flags: Vec<String>,
+
+    /// The next unique identifier value. Refer to [`RFC 3501`, section
+    /// 2.3.1.1](https://tools.ietf.org/html/rfc3501#section-2.3.1.1) for more information.
+    pub uid_next: Option<u32>,
+
+    /// The unique identifier validity value. Refer to [`RFC 3501`, section
+    /// 2.3.1.1](https://tools.ietf.org/html/rfc3501#section-2.3.1.1) for more information.
+    pub uid_validity: Option<u32>,
+
+    /// The highest mod-sequence value of all messages in the mailbox, reported when the server
+    /// supports `CONDSTORE` (RFC 7162). `None` both when the server didn't report it and when it
+    /// replied `NOMODSEQ` (the mailbox doesn't support persistent mod-sequences).
+    ///
+    /// This only increases for as long as `uid_validity` stays the same; a stored `highest_mod_seq`
+    /// must be discarded along with any cached UIDs if a later `SELECT` reports a different
+    /// `uid_validity`, since the server is free to reassign UIDs (and restart mod-sequences) when
+    /// that happens.
+    pub highest_mod_seq: Option<u64>,
+
+    /// Whether the mailbox was opened read-only, as reported by the `[READ-ONLY]`/`[READ-WRITE]`
+    /// response code on the tagged `SELECT`/`EXAMINE` completion. `false` (the default) both when
+    /// the server reported `[READ-WRITE]` and when it reported neither.
+    pub read_only: bool,
+}
@@ -0,0 +1,137 @@
+//! Types used to represent the data returned by the IMAP server in response to various commands.
+
+mod fetch;
+mod mailbox;
+mod name;
+
+pub use self::fetch::{Address, BodyStructure, Envelope, Fetch};
+pub use self::mailbox::Mailbox;
+pub use self::name::Name;
+
+use std::collections::HashSet;
+
+use super::error::Result;
+
+/// The `Result` type returned by the methods that parse a server response into the types of this
+/// module. Despite the name, the values it carries own their data: it is copied out of the
+/// response buffer.
+pub type ZeroCopyResult<T> = Result<T>;
+
+/// An untagged response that the server sent without being solicited by the command currently in
+/// progress. Per [RFC 3501 section 7](https://tools.ietf.org/html/rfc3501#section-7), the server
+/// is allowed to send these at (almost) any time, and clients that keep a mailbox selected for a
+/// long time need some way to observe them; they are made available through
+/// [`Session::unsolicited_responses`](../client/struct.Session.html#structfield.unsolicited_responses).
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum UnsolicitedResponse {
+    /// The number of messages in the mailbox has changed. See [RFC 3501, section
+    /// 7.3.1](https://tools.ietf.org/html/rfc3501#section-7.3.1).
+    Exists(u32),
+    /// The number of messages with the `\Recent` flag set has changed. See [RFC 3501, section
+    /// 7.3.2](https://tools.ietf.org/html/rfc3501#section-7.3.2).
+    Recent(u32),
+    /// The message with the given sequence number has been permanently removed from the
+    /// mailbox. See [RFC 3501, section 7.4.1](https://tools.ietf.org/html/rfc3501#section-7.4.1).
+    Expunge(u32),
+    /// A message's attributes have changed, most commonly its flags (e.g. in response to another
+    /// client marking it `\Seen` or `\Deleted`). See [RFC 3501, section
+    /// 7.4.2](https://tools.ietf.org/html/rfc3501#section-7.4.2).
+    Fetch(Fetch),
+    /// The given UIDs have been expunged from the mailbox. Sent instead of individual `Expunge`
+    /// responses by a `QRESYNC`-enabled connection (RFC 7162), most commonly as part of the
+    /// `VANISHED (EARLIER)` data a `QRESYNC` `SELECT` returns for messages removed since the
+    /// client's last-known mod-sequence.
+    Vanished(Vec<u32>),
+}
+
+/// The result of a `CAPABILITY` command: the set of capabilities the server advertised.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Capabilities(pub(crate) Vec<String>);
+
+impl Capabilities {
+    /// Returns whether the given capability is supported by the server.
+    pub fn has(&self, capability: &str) -> bool {
+        self.0.iter().any(|s| s.eq_ignore_ascii_case(capability))
+    }
+
+    /// Returns whether the server advertised support for the given SASL authentication
+    /// mechanism, i.e. whether it sent an `AUTH={mechanism}` capability.
+    pub fn has_auth(&self, mechanism: &str) -> bool {
+        self.0.iter().any(|s| {
+            s.len() > 5 && s[..5].eq_ignore_ascii_case("AUTH=") && s[5..].eq_ignore_ascii_case(mechanism)
+        })
+    }
+
+    /// Whether the server supports the CONDSTORE extension ([RFC
+    /// 7162](https://tools.ietf.org/html/rfc7162)).
+    pub fn supports_condstore(&self) -> bool {
+        self.has("CONDSTORE")
+    }
+
+    /// Whether the server supports the IDLE extension ([RFC
+    /// 2177](https://tools.ietf.org/html/rfc2177)).
+    pub fn supports_idle(&self) -> bool {
+        self.has("IDLE")
+    }
+
+    /// Whether the server supports UTF-8 mailbox and message data via `UTF8=ACCEPT` ([RFC
+    /// 6855](https://tools.ietf.org/html/rfc6855)).
+    pub fn supports_utf8_accept(&self) -> bool {
+        self.has("UTF8=ACCEPT")
+    }
+
+    /// The number of capabilities the server advertised.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the server advertised no capabilities at all.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// An iterator over the raw capability strings the server advertised.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|s| s.as_str())
+    }
+}
+
+/// The UID the server assigned to a newly-appended message, reported in the `[APPENDUID
+/// <uidvalidity> <uid>]` response code ([RFC 4315](https://tools.ietf.org/html/rfc4315#section-3))
+/// when the server supports it.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct AppendUid {
+    /// The UIDVALIDITY of the mailbox the message was appended to.
+    pub uid_validity: u32,
+    /// The UID assigned to the newly-appended message.
+    pub uid: u32,
+}
+
+/// The result of a `search`/`uid_search` call made with RFC 4731 return options: the `* ESEARCH
+/// (TAG "...") [UID] [MIN n] [MAX n] [COUNT n] [ALL <seq-set>]` response, or its effective
+/// equivalent if the server fell back to a plain `* SEARCH ...` response.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct SearchResult {
+    /// Whether `min`/`max`/`all` are UIDs (as opposed to message sequence numbers).
+    pub uid: bool,
+    /// The lowest id among the matching messages, if the `MIN` return option was requested.
+    pub min: Option<u32>,
+    /// The highest id among the matching messages, if the `MAX` return option was requested.
+    pub max: Option<u32>,
+    /// The number of matching messages, if the `COUNT` return option was requested.
+    pub count: Option<u32>,
+    /// Every id among the matching messages, if the `ALL` return option was requested.
+    pub all: HashSet<u32>,
+}
+
+/// The source→destination UID mapping from a successful `COPY`/`MOVE`/`UID MOVE`, reported in the
+/// `[COPYUID <uidvalidity> <source-uid-set> <dest-uid-set>]` response code ([RFC
+/// 4315](https://tools.ietf.org/html/rfc4315#section-3)) when the server supports UIDPLUS.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CopyUid {
+    /// The UIDVALIDITY of the destination mailbox.
+    pub uid_validity: u32,
+    /// `(source_uid, destination_uid)` pairs, positionally zipped from the two uid-sets the
+    /// server reported, in the order it reported them.
+    pub uids: Vec<(u32, u32)>,
+}
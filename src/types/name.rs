@@ -0,0 +1,24 @@
+use super::super::mutf7;
+
+/// A name that matches a `LIST` or `LSUB` command.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Name {
+    /// Attributes of this name.
+    pub attributes: Vec<String>,
+    /// The hierarchy delimiter is a character used to delimit levels of hierarchy in a mailbox
+    /// name.
+    pub delimiter: String,
+    /// The name represents an unambiguous left-to-right hierarchy, and is not necessarily a
+    /// unique textual string. This is the raw, on-the-wire form: modified UTF-7 ([RFC 3501
+    /// section 5.1.3](https://tools.ietf.org/html/rfc3501#section-5.1.3)), not necessarily valid
+    /// Unicode on its own. Use [`Name::name`] for the decoded form.
+    pub raw_name: String,
+}
+
+impl Name {
+    /// The mailbox name, decoded from its modified UTF-7 wire encoding ([RFC 3501 section
+    /// 5.1.3](https://tools.ietf.org/html/rfc3501#section-5.1.3)).
+    pub fn name(&self) -> String {
+        mutf7::decode(&self.raw_name)
+    }
+}